@@ -0,0 +1,60 @@
+//! Benchmarks the day-19 scanner-cloud solver against the real puzzle
+//! input. Day 19 is the heaviest computation in the crate
+//! (`O(scanners^2 * rotations * beacons^2)` in the worst case), so this
+//! keeps the fingerprint prefilter and the full rotation/position search
+//! it guards benchmarked separately: a regression in either shows up on
+//! its own instead of being buried in the end-to-end time, and a win from
+//! the prefilter (fewer pairs reaching the expensive search) is visible
+//! as a shrinking gap between the two.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/bin/day19.rs"]
+#[allow(dead_code)]
+mod day19;
+
+const INPUT: &str = include_str!("../input/day19.txt");
+
+fn bench_fingerprint_prefilter(c: &mut Criterion) {
+    let mut scanners = day19::read_scanners(INPUT);
+    scanners.iter_mut().for_each(|s| s.distance_calc());
+
+    c.bench_function("day19_fingerprint_overlaps", |b| {
+        b.iter(|| {
+            for i in 0..scanners.len() {
+                for j in (i + 1)..scanners.len() {
+                    black_box(scanners[i].overlaps(&scanners[j]));
+                }
+            }
+        })
+    });
+}
+
+fn bench_rotation_search(c: &mut Criterion) {
+    let mut scanners = day19::read_scanners(INPUT);
+    scanners.iter_mut().for_each(|s| s.distance_calc());
+    let reference = scanners[0].clone();
+
+    c.bench_function("day19_likely_rotation_and_pos", |b| {
+        b.iter(|| {
+            for scanner in &scanners[1..] {
+                black_box(
+                    scanner
+                        .likely_rotation_and_pos(&reference)
+                        .or_else(|| scanner.align_by_offset_voting(&reference)),
+                );
+            }
+        })
+    });
+}
+
+fn bench_full_reconstruction(c: &mut Criterion) {
+    c.bench_function("day19_solve", |b| b.iter(|| black_box(day19::solve(INPUT))));
+}
+
+criterion_group!(
+    benches,
+    bench_fingerprint_prefilter,
+    bench_rotation_search,
+    bench_full_reconstruction
+);
+criterion_main!(benches);
@@ -0,0 +1,90 @@
+/// A reusable engine for cellular automata that live on an infinite 2D plane:
+/// a finite grid of cells plus a single `background` value that every cell
+/// outside the grid shares (and which can itself change every step, as in
+/// Day 20's "flashing" background).
+///
+/// The step mechanics (growing the stored grid by one ring per step, reading
+/// out-of-bounds neighbors as `background`) are the same regardless of what
+/// the rule actually computes, so they live here once and the rule is
+/// supplied by the caller.
+pub struct InfiniteGrid<T> {
+    pub cells: Vec<Vec<T>>,
+    pub background: T,
+}
+
+/// A pluggable rule for an `InfiniteGrid` step: given the 3x3 neighborhood of
+/// a cell (row-major, so the cell itself is at index 4) and the current background, compute the
+/// cell's next value; and given the current background, compute the next
+/// background.
+pub trait Rule<T> {
+    fn next_value(&self, neighborhood: [T; 9], background: T) -> T;
+    fn next_background(&self, background: T) -> T;
+}
+
+impl<T: Copy> InfiniteGrid<T> {
+    pub fn new(cells: Vec<Vec<T>>, background: T) -> InfiniteGrid<T> {
+        InfiniteGrid { cells, background }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Grow the stored grid by exactly one ring of `background`-valued cells,
+    /// so that every cell we are about to compute has a full neighborhood
+    /// available without needing special-casing at the edges.
+    fn grown(&self) -> Vec<Vec<T>> {
+        let (width, height) = (self.width(), self.height());
+        let mut grown = vec![vec![self.background; width + 2]; height + 2];
+        for y in 0..height {
+            for x in 0..width {
+                grown[y + 1][x + 1] = self.cells[y][x];
+            }
+        }
+        grown
+    }
+
+    fn read(grid: &[Vec<T>], x: i32, y: i32, background: T) -> T {
+        if x >= 0 && x < grid[0].len() as i32 && y >= 0 && y < grid.len() as i32 {
+            grid[y as usize][x as usize]
+        } else {
+            background
+        }
+    }
+
+    /// Advance the automaton by one step using the given rule.
+    pub fn step<R: Rule<T>>(&self, rule: &R) -> InfiniteGrid<T> {
+        let grown = self.grown();
+        let (width, height) = (grown[0].len(), grown.len());
+
+        let mut next = Vec::with_capacity(height);
+        for y in 0..height {
+            let mut row = Vec::with_capacity(width);
+            for x in 0..width {
+                let (xi, yi) = (x as i32, y as i32);
+                let neighborhood = [
+                    Self::read(&grown, xi - 1, yi - 1, self.background),
+                    Self::read(&grown, xi, yi - 1, self.background),
+                    Self::read(&grown, xi + 1, yi - 1, self.background),
+                    Self::read(&grown, xi - 1, yi, self.background),
+                    Self::read(&grown, xi, yi, self.background),
+                    Self::read(&grown, xi + 1, yi, self.background),
+                    Self::read(&grown, xi - 1, yi + 1, self.background),
+                    Self::read(&grown, xi, yi + 1, self.background),
+                    Self::read(&grown, xi + 1, yi + 1, self.background),
+                ];
+                row.push(rule.next_value(neighborhood, self.background));
+            }
+            next.push(row);
+        }
+
+        InfiniteGrid {
+            cells: next,
+            background: rule.next_background(self.background),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! A single entry point that dispatches to whichever day's `Solution` is
+//! registered, so a day can be run as `cargo run --bin aoc -- <day>`
+//! instead of needing its own `--bin dayN` target. (Configuring
+//! `default-run = "aoc"` for this binary in `Cargo.toml` would additionally
+//! make plain `cargo run -- <day>` work.)
+//!
+//! Each day still builds as its own standalone binary (`src/bin/dayN.rs`,
+//! with its own `pub fn main()`); this runner pulls the same source in as
+//! a module so the two stay in sync without duplicating any logic.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
+use aoc2021::solution::Solution;
+
+#[path = "day3.rs"]
+#[allow(dead_code)]
+mod day3;
+#[path = "day8.rs"]
+#[allow(dead_code)]
+mod day8;
+#[path = "day11.rs"]
+#[allow(dead_code)]
+mod day11;
+#[path = "day25.rs"]
+#[allow(dead_code)]
+mod day25;
+
+fn registry() -> HashMap<u32, Box<dyn Solution>> {
+    let mut solutions: HashMap<u32, Box<dyn Solution>> = HashMap::new();
+    solutions.insert(3, Box::new(day3::Day3));
+    solutions.insert(8, Box::new(day8::Day8));
+    solutions.insert(11, Box::new(day11::Day11));
+    solutions.insert(25, Box::new(day25::Day25));
+    solutions
+}
+
+pub fn main() {
+    let day: u32 = env::args()
+        .nth(1)
+        .expect("usage: aoc <day>")
+        .parse()
+        .expect("day must be a number");
+
+    let solutions = registry();
+    let solution = solutions
+        .get(&day)
+        .unwrap_or_else(|| panic!("no solution registered for day {}", day));
+
+    let input = fs::read_to_string(format!("input/day{}.txt", day)).expect("file not found");
+    match solution.run(&input) {
+        Ok(output) => println!("{}", output),
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
@@ -1,196 +1,165 @@
-use colored::Colorize;
+use std::collections::HashSet;
 
-use aoc2021::read_strs;
+use colored::Colorize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Octopus {
-    Idle(usize),
-    Flashing,
-    HasFlashed,
+use aoc2021::grid::Grid;
+use aoc2021::solution::{Result, Solution};
+
+fn parse_grid(input: &str) -> Grid<u32> {
+    let width = input.lines().next().unwrap().len();
+    let height = input.lines().count();
+    let cells = input
+        .lines()
+        .flat_map(|line| line.chars().map(|c| c as u32 - '0' as u32))
+        .collect();
+    Grid::new(width, height, cells)
 }
 
-impl Octopus {
-    fn increase(&mut self) {
-        match self {
-            Octopus::Idle(i) => *i += 1,
-            Octopus::Flashing => {}
-            Octopus::HasFlashed => {}
-        }
-    }
-
-    fn should_flash(&self) -> bool {
-        matches!(self, Octopus::Idle(e) if *e > 9)
-    }
-
-    fn flash_if_should(&mut self) -> u32 {
-        if self.should_flash() {
-            *self = Octopus::Flashing;
-            return 1;
-        }
-        0
-    }
-
-    fn reset_if_flashed(&mut self) {
-        if matches!(self, Octopus::HasFlashed) {
-            *self = Octopus::Idle(0);
+fn print_grid(grid: &Grid<u32>) {
+    for row in 0..grid.height() {
+        for col in 0..grid.width() {
+            match grid.get(row, col).unwrap() {
+                0 => print!("{}", String::from("0").yellow()),
+                e => print!("{}", e.to_string().blue()),
+            }
         }
+        println!();
     }
 }
 
-fn read_octopi(file_path: &str) -> Vec<Vec<Octopus>> {
-    let lines = read_strs(file_path);
-    let mut octopi = Vec::new();
-    for line in lines {
-        let mut row = Vec::new();
-        for c in line.chars() {
-            row.push(Octopus::Idle(c as usize - '0' as usize));
+/// Increase every cell's energy by one, then propagate flashes with an
+/// explicit worklist: cells that cross the threshold are pushed onto a
+/// stack and recorded in `flashed`, and popping a cell increments its
+/// neighbors, pushing any neighbor that newly crosses the threshold (and
+/// isn't already in `flashed`, so it can only flash once this step).
+/// Returns the number of cells that flashed, after resetting them to 0.
+fn step(grid: &mut Grid<u32>) -> usize {
+    let mut stack = Vec::new();
+    let mut flashed: HashSet<(usize, usize)> = HashSet::new();
+
+    for row in 0..grid.height() {
+        for col in 0..grid.width() {
+            let energy = grid.get_mut(row, col).unwrap();
+            *energy += 1;
+            if *energy > 9 {
+                stack.push((row, col));
+                flashed.insert((row, col));
+            }
         }
-        octopi.push(row);
     }
-    octopi
-}
 
-fn print_octopi(octopi: &[Vec<Octopus>]) {
-    for row in octopi {
-        for octopus in row {
-            match octopus {
-                Octopus::Idle(0) => print!("{}", String::from("0").yellow()),
-                Octopus::Idle(e) => print!("{}", e.to_string().blue()),
-                Octopus::Flashing => print!("{}", String::from("*").black().on_white()),
-                Octopus::HasFlashed => print!("{}", String::from("0").black().on_white()),
+    while let Some((row, col)) = stack.pop() {
+        let neighbors: Vec<_> = grid.neighbors8(row, col).collect();
+        for (nrow, ncol) in neighbors {
+            let energy = grid.get_mut(nrow, ncol).unwrap();
+            *energy += 1;
+            if *energy > 9 && !flashed.contains(&(nrow, ncol)) {
+                flashed.insert((nrow, ncol));
+                stack.push((nrow, ncol));
             }
         }
-        println!();
     }
-}
 
-fn has_octopi_that_should_flash(octopi: &[Vec<Octopus>]) -> bool {
-    for row in octopi {
-        for octopus in row {
-            if octopus.should_flash() {
-                return true;
-            }
-        }
+    for &(row, col) in &flashed {
+        *grid.get_mut(row, col).unwrap() = 0;
     }
-    false
+
+    flashed.len()
 }
 
-/// This function processes the octopi in the given octopi matrix.
-/// First, increase the energy of all the octopuses by one.
-/// Then, search for "flashing" octopi.
-/// An octopus with energy level greater than 9 will become "flashing"
-/// For every "flashing" octopus, increase it's neighbours energy by one.
-/// If any of the neighbors increase energy above 9, they become "flashing" too
-/// and spread their energy to their neighbours.
-/// This continues until no more octopi have energy levels above 9.
-/// The last step, every flashing octopus is set to zero.
-fn step(octopi: &mut [Vec<Octopus>]) -> u32 {
-    // increase all energy by one
-    for row in 0..octopi.len() {
-        for col in 0..octopi[0].len() {
-            octopi[row][col].increase();
+/// Step from 1 until every cell flashes in the same step, returning that
+/// step number.
+fn first_all_flash(grid: &mut Grid<u32>) -> usize {
+    let total = grid.width() * grid.height();
+    let mut i = 0;
+    loop {
+        i += 1;
+        if step(grid) == total {
+            return i;
         }
     }
+}
 
-    // as long as there are octopi with energy levels above 9
-    // do the flashing step
-
-    // keep count of the flashes
-    let mut flashes: u32 = 0;
+pub struct Day11;
 
-    while has_octopi_that_should_flash(octopi) {
-        for row in 0..octopi.len() {
-            for col in 0..octopi[0].len() {
-                flashes += octopi[row][col].flash_if_should();
-            }
-        }
-
-        // increase neighbours of flashing octopi energy by one
-        for row in 0..octopi.len() {
-            for col in 0..octopi[0].len() {
-                let octopus = &octopi[row][col];
-                if let Octopus::Flashing = octopus {
-                    if row > 0 {
-                        octopi[row - 1][col].increase();
-                        if col > 0 {
-                            octopi[row - 1][col - 1].increase();
-                        }
-                        if col < octopi[0].len() - 1 {
-                            octopi[row - 1][col + 1].increase();
-                        }
-                    }
-                    if row < octopi.len() - 1 {
-                        octopi[row + 1][col].increase();
-                        if col > 0 {
-                            octopi[row + 1][col - 1].increase();
-                        }
-                        if col < octopi[0].len() - 1 {
-                            octopi[row + 1][col + 1].increase();
-                        }
-                    }
-                    if col > 0 {
-                        octopi[row][col - 1].increase();
-                    }
-                    if col < octopi[0].len() - 1 {
-                        octopi[row][col + 1].increase();
-                    }
-                    // Mark the octopus as "has flashed"
-                    octopi[row][col] = Octopus::HasFlashed;
-                }
-            }
-        }
+impl Solution for Day11 {
+    fn part1(&self, input: &str) -> Result<String> {
+        let mut grid = parse_grid(input);
+        let total_flashes: usize = (0..100).map(|_| step(&mut grid)).sum();
+        Ok(total_flashes.to_string())
     }
 
-    // set all flashing octopi to zero
-    for row in 0..octopi.len() {
-        for col in 0..octopi[0].len() {
-            octopi[row][col].reset_if_flashed();
-        }
+    fn part2(&self, input: &str) -> Result<String> {
+        let mut grid = parse_grid(input);
+        Ok(first_all_flash(&mut grid).to_string())
     }
-
-    flashes
 }
 
-fn day11() {
-    let mut octopi = read_octopi("input/day11.txt");
-    let mut total_flashes = 0;
+pub fn main() {
+    let input = std::fs::read_to_string("input/day11.txt").expect("file not found");
+    let grid = parse_grid(&input);
     println!("Before any steps:");
-    print_octopi(&octopi);
-
-    for i in 1..101 {
-        let flashes = step(&mut octopi);
-        total_flashes += flashes;
+    print_grid(&grid);
 
-        if i % 10 == 0 {
-            println!("\nAfter step {}:", i);
-            print_octopi(&octopi);
-            println!("Total flashes: {}", total_flashes);
-        }
+    match Day11.run(&input) {
+        Ok(output) => println!("{}", output),
+        Err(e) => eprintln!("error: {}", e),
     }
 }
 
-fn day11b() {
-    let mut octopi = read_octopi("input/day11.txt");
-
-    // get the total number of octopi
-    let total_octopi: u32 = octopi.iter().map(|row| row.len()).sum::<usize>() as u32;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_example() -> Grid<u32> {
+        parse_grid(
+            "11111\n\
+             19991\n\
+             19191\n\
+             19991\n\
+             11111",
+        )
+    }
 
-    println!("Total nr of octopi: {}", total_octopi);
+    fn example() -> &'static str {
+        "5483143223\n\
+         2745854711\n\
+         5264556173\n\
+         6141336146\n\
+         6357385478\n\
+         4167524645\n\
+         2176841721\n\
+         6882881134\n\
+         4846848554\n\
+         5283751526"
+    }
 
-    for i in 1..1001 {
-        let flashes = step(&mut octopi);
-        print!(".");
+    #[test]
+    fn test_small_example_two_steps() {
+        let mut grid = small_example();
+        step(&mut grid);
+        step(&mut grid);
+
+        let expected = parse_grid(
+            "45654\n\
+             51115\n\
+             61116\n\
+             51115\n\
+             45654",
+        );
+        assert_eq!(grid, expected);
+    }
 
-        // if the number of flashes equals the number of octopi, they all flashed :-)
-        if flashes == total_octopi {
-            println!("\nAfter step {}:", i);
-            print_octopi(&octopi);
-            break;
-        }
+    #[test]
+    fn test_example_flashes_after_10_steps() {
+        let mut grid = parse_grid(example());
+        let total: usize = (0..10).map(|_| step(&mut grid)).sum();
+        assert_eq!(total, 204);
     }
-}
 
-pub fn main() {
-    day11();
-    day11b();
+    #[test]
+    fn test_solution_parts() {
+        assert_eq!(Day11.part1(example()).unwrap(), "1656");
+        assert_eq!(Day11.part2(example()).unwrap(), "195");
+    }
 }
@@ -1,12 +1,16 @@
-use std::{str::FromStr, collections::{HashMap, HashSet}, fmt};
 use aoc2021::read_strs;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
 
 #[derive(PartialEq, Eq, Clone, Hash)]
 enum Node {
     Start,
     End,
     SmallCave(String),
-    BigCave(String)
+    BigCave(String),
 }
 
 impl FromStr for Node {
@@ -39,24 +43,22 @@ impl fmt::Debug for Node {
             Node::Start => write!(f, "start"),
             Node::End => write!(f, "end"),
             Node::SmallCave(c) => write!(f, "{}", c),
-            Node::BigCave(c) => write!(f, "{}", c)
+            Node::BigCave(c) => write!(f, "{}", c),
         }
     }
 }
 
 struct Map {
-    conn: HashMap<Node, Vec<Node>>
+    conn: HashMap<Node, Vec<Node>>,
 }
 
-
-
 impl Map {
     fn from_lines(lines: &[String]) -> Map {
         // create empty map
         let mut map = Map {
-            conn: HashMap::new()
+            conn: HashMap::new(),
         };
-        
+
         // for each line, run parse_line
         for line in lines {
             map.parse_line(line.clone());
@@ -146,7 +148,12 @@ impl Map {
         paths
     }
 
-    fn get_paths_p2_recursive(&self, node: &Node, path: &mut Vec<Node>, paths: &mut Vec<Vec<Node>>) {
+    fn get_paths_p2_recursive(
+        &self,
+        node: &Node,
+        path: &mut Vec<Node>,
+        paths: &mut Vec<Vec<Node>>,
+    ) {
         // if we've reached the end node, add the path to the list of paths
         if node == &Node::End {
             paths.push(path.clone());
@@ -164,12 +171,11 @@ impl Map {
         for connection in connections {
             // if the connection is a small cave
             if let Node::SmallCave(_) = connection {
-                
-                // the trick now is that we're able to visit one small cave 
+                // the trick now is that we're able to visit one small cave
                 // twice, so we need to know if we already have a path that
                 // includes a "double visit" of a small cave.
                 if contains_double_visit(path) {
-                    // if so, we can't revisit this small cave, so        
+                    // if so, we can't revisit this small cave, so
                     // if the path already contains this connection, skip it.
                     if path.contains(connection) {
                         continue;
@@ -178,7 +184,6 @@ impl Map {
                     // our path does not yet contain a double visit, so we can
                     // visit this small cave again.
                 }
-
             }
 
             path.push(connection.clone());
@@ -186,6 +191,98 @@ impl Map {
             path.pop();
         }
     }
+
+    /// Assigns each small cave a distinct bit position, so a path's
+    /// already-visited set of small caves can be tracked as a `u64` bitmask
+    /// instead of cloning nodes into a `Vec`/`HashSet`. Big caves (and
+    /// start/end) are never added, since they're never subject to the
+    /// revisit rules.
+    fn small_cave_indices(&self) -> HashMap<Node, u8> {
+        self.conn
+            .keys()
+            .filter(|n| matches!(n, Node::SmallCave(_)))
+            .cloned()
+            .enumerate()
+            .map(|(i, n)| (n, i as u8))
+            .collect()
+    }
+
+    /// Count the number of paths from `start` to `end`, without ever
+    /// materializing them: the recurrence sums completions over each
+    /// neighbor, memoized on `(node, visited small caves, already used the
+    /// one extra small-cave visit)` so the exponential blowup of
+    /// overlapping sub-paths is only computed once. When `allow_one_double`
+    /// is set, exactly one small cave may be visited twice over the whole
+    /// path; otherwise every small cave is visited at most once.
+    fn count_paths(&self, allow_one_double: bool) -> usize {
+        let indices = self.small_cave_indices();
+        let mut memo = HashMap::new();
+        self.count_paths_recursive(
+            &Node::Start,
+            0,
+            false,
+            allow_one_double,
+            &indices,
+            &mut memo,
+        )
+    }
+
+    fn count_paths_recursive(
+        &self,
+        node: &Node,
+        visited: u64,
+        double_used: bool,
+        allow_double: bool,
+        indices: &HashMap<Node, u8>,
+        memo: &mut HashMap<(Node, u64, bool), usize>,
+    ) -> usize {
+        if node == &Node::End {
+            return 1;
+        }
+
+        let key = (node.clone(), visited, double_used);
+        if let Some(&count) = memo.get(&key) {
+            return count;
+        }
+
+        let mut total = 0;
+        for next in &self.conn[node] {
+            if next == &Node::Start {
+                continue;
+            }
+
+            total += match indices.get(next) {
+                // a big cave (or start/end): never tracked in the mask.
+                None => self.count_paths_recursive(
+                    next,
+                    visited,
+                    double_used,
+                    allow_double,
+                    indices,
+                    memo,
+                ),
+                // an unvisited small cave: mark it visited going forward.
+                Some(&bit) if visited & (1 << bit) == 0 => self.count_paths_recursive(
+                    next,
+                    visited | (1 << bit),
+                    double_used,
+                    allow_double,
+                    indices,
+                    memo,
+                ),
+                // an already-visited small cave: only revisitable once,
+                // part 2 only, and only if that one revisit hasn't
+                // happened yet on this path.
+                Some(_) if allow_double && !double_used => {
+                    self.count_paths_recursive(next, visited, true, allow_double, indices, memo)
+                }
+                Some(_) => 0,
+            };
+        }
+
+        memo.insert(key, total);
+        total
+    }
 }
 
 fn contains_double_visit(path: &[Node]) -> bool {
@@ -200,17 +297,12 @@ fn contains_double_visit(path: &[Node]) -> bool {
     small_caves.len() != small_caves.iter().collect::<HashSet<_>>().len()
 }
 
-
 pub fn main() {
     let input = read_strs("input/day12.txt");
     let map = Map::from_lines(&input);
     println!("MAP: {:?}", map.conn);
-    let paths = map.get_paths();
-    println!("{} PATHS:", paths.len());
-    for path in paths {
-        println!("{:?}", path);
-    }
-    println!("Part 2: {} PATHS:", map.get_paths_p2().len());
+    println!("Part 1: {} paths", map.count_paths(false));
+    println!("Part 2: {} paths", map.count_paths(true));
 }
 
 #[cfg(test)]
@@ -219,10 +311,7 @@ mod tests {
 
     #[test]
     fn test_read_map() {
-        let map = Map::from_lines(vec!(
-            "start-A".to_string(),
-            "A-end".to_string(),
-        ).as_slice());
+        let map = Map::from_lines(vec!["start-A".to_string(), "A-end".to_string()].as_slice());
 
         assert_eq!(map.conn.len(), 3);
 
@@ -233,13 +322,16 @@ mod tests {
 
     #[test]
     fn test_paths() {
-        let map = Map::from_lines(vec!(
-            "start-A".to_string(),
-            "A-b".to_string(),
-            "A-c".to_string(),
-            "b-C".to_string(),
-            "c-end".to_string(),
-        ).as_slice());
+        let map = Map::from_lines(
+            vec![
+                "start-A".to_string(),
+                "A-b".to_string(),
+                "A-c".to_string(),
+                "b-C".to_string(),
+                "c-end".to_string(),
+            ]
+            .as_slice(),
+        );
 
         let paths = map.get_paths();
         assert_eq!(paths.len(), 2);
@@ -292,4 +384,30 @@ mod tests {
         let paths = map.get_paths_p2();
         assert_eq!(paths.len(), 3509);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_count_paths_matches_enumeration() {
+        for (file, expected) in [
+            ("input/day12_ex.txt", 10),
+            ("input/day12_ex2.txt", 19),
+            ("input/day12_ex3.txt", 226),
+        ] {
+            let map = Map::from_lines(&read_strs(file));
+            assert_eq!(map.count_paths(false), expected);
+            assert_eq!(map.count_paths(false), map.get_paths().len());
+        }
+    }
+
+    #[test]
+    fn test_count_paths_p2_matches_enumeration() {
+        for (file, expected) in [
+            ("input/day12_ex.txt", 36),
+            ("input/day12_ex2.txt", 103),
+            ("input/day12_ex3.txt", 3509),
+        ] {
+            let map = Map::from_lines(&read_strs(file));
+            assert_eq!(map.count_paths(true), expected);
+            assert_eq!(map.count_paths(true), map.get_paths_p2().len());
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{collections::HashSet, str::FromStr};
 
+use aoc2021::grid::CoverageGrid;
 use aoc2021::read_strs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,6 +27,35 @@ impl FromStr for Fold {
     }
 }
 
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+const GLYPH_STRIDE: usize = GLYPH_WIDTH + 1;
+
+/// The 4x6 dot pattern (`#` inked, `.` blank) for every letter that AoC's
+/// Day 13 font can render, read top to bottom. Letters this font never
+/// draws (D, M, N, Q, T, V, W, X) are simply absent from the table.
+#[rustfmt::skip]
+const GLYPHS: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#..#", "#..#", ".##.", "..#.", "..#.", "..#."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
 struct Paper {
     dots: HashSet<(i32, i32)>,
     folds: Vec<Fold>,
@@ -112,38 +142,53 @@ impl Paper {
         self.dots.len()
     }
 
+    /// Decode the dots into the string of capital letters they spell.
+    /// Normalizes to the bounding box, splits it into the standard
+    /// 5-pixel-wide columns (4 dot-columns plus a 1-pixel gap) these
+    /// puzzles use, and matches each 4x6 glyph against `GLYPHS`. A glyph
+    /// that doesn't match any known letter decodes to `?` so the rest of
+    /// the string is still readable.
+    fn read_letters(&self) -> String {
+        let min_x = self.dots.iter().map(|(x, _)| *x).min().unwrap_or(0);
+        let min_y = self.dots.iter().map(|(_, y)| *y).min().unwrap_or(0);
+        let max_x = self.dots.iter().map(|(x, _)| *x).max().unwrap_or(0);
+
+        let width = (max_x - min_x + 1) as usize;
+        let letter_count = (width + 1) / GLYPH_STRIDE;
+
+        (0..letter_count)
+            .map(|i| {
+                let origin_x = min_x + (i * GLYPH_STRIDE) as i32;
+
+                let rows: Vec<String> = (0..GLYPH_HEIGHT)
+                    .map(|row| {
+                        (0..GLYPH_WIDTH)
+                            .map(|col| {
+                                let pos = (origin_x + col as i32, min_y + row as i32);
+                                if self.dots.contains(&pos) {
+                                    '#'
+                                } else {
+                                    '.'
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                GLYPHS
+                    .iter()
+                    .find(|(_, glyph)| rows.iter().zip(glyph.iter()).all(|(r, g)| r.as_str() == *g))
+                    .map(|(letter, _)| *letter)
+                    .unwrap_or('?')
+            })
+            .collect()
+    }
+
     /// This function prints out a grid of dots, with dots marked with a #
     fn print_dots(&self) {
-        // first we need to find the max x and y values
-        let mut max_x = std::i32::MIN;
-        let mut max_y = std::i32::MIN;
-
-        for dot in &self.dots {
-            let (x, y) = dot;
-            if *x > max_x {
-                max_x = *x;
-            }
-            if *y > max_y {
-                max_y = *y;
-            }
-        }
-
-        // create a char vec to hold the grid
-        let mut grid = vec![' '; (max_x + 1) as usize * (max_y + 1) as usize];
-
-        // loop through the dots and mark them with a #
-        for dot in &self.dots {
-            let (x, y) = dot;
-            grid[(x + y * (max_x + 1)) as usize] = '#';
-        }
-
-        // print the grid
-        for y in 0..=max_y {
-            for x in 0..=max_x {
-                print!("{}", grid[(x + y * (max_x + 1)) as usize]);
-            }
-            println!();
-        }
+        let mut grid = CoverageGrid::new();
+        grid.add_points(self.dots.iter().copied());
+        println!("{}", grid.render());
     }
 }
 
@@ -164,6 +209,9 @@ pub fn main() {
 
     // print the grid
     paper.print_dots();
+
+    // decode the grid into the letters it spells
+    println!("Part 2: {}", paper.read_letters());
 }
 
 #[cfg(test)]
@@ -205,4 +253,48 @@ mod tests {
         // zero folds should remain
         assert_eq!(paper.folds.len(), 0);
     }
+
+    /// Build a `Paper` straight from a multi-letter glyph grid, skipping
+    /// the file/fold machinery, so `read_letters` can be tested directly.
+    fn paper_from_glyphs(rows: &[&str]) -> Paper {
+        let dots = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| (x as i32, y as i32))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Paper {
+            dots,
+            folds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_read_letters() {
+        // "AB" rendered side by side, with the usual 1-pixel column gap.
+        let paper = paper_from_glyphs(&[
+            ".##. ###.",
+            "#..# #..#",
+            "#..# ###.",
+            "#### #..#",
+            "#..# #..#",
+            "#..# ###.",
+        ]);
+
+        assert_eq!(paper.read_letters(), "AB");
+    }
+
+    #[test]
+    fn test_read_letters_unrecognized_glyph() {
+        // A glyph that isn't in the font decodes to `?`.
+        let paper = paper_from_glyphs(&["####", "####", "####", "####", "####", "####"]);
+
+        assert_eq!(paper.read_letters(), "?");
+    }
 }
@@ -1,7 +1,7 @@
 use aoc2021::read_strs;
 
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 struct State {
@@ -83,6 +83,324 @@ fn shortest_path(adj_list: &[Vec<Edge>], start: usize, goal: usize) -> Option<us
     None
 }
 
+/// Same relaxation as `shortest_path`, but also tracks `prev` so the
+/// winning route itself can be recovered, not just its total cost. Useful
+/// for rendering the chiton grid with the path marked.
+fn shortest_path_with_route(
+    adj_list: &[Vec<Edge>],
+    start: usize,
+    goal: usize,
+) -> Option<(usize, Vec<usize>)> {
+    let mut dist: Vec<_> = (0..adj_list.len()).map(|_| usize::MAX).collect();
+    let mut prev: Vec<Option<usize>> = vec![None; adj_list.len()];
+
+    let mut heap = BinaryHeap::new();
+
+    dist[start] = 0;
+    heap.push(State {
+        cost: 0,
+        position: start,
+    });
+
+    while let Some(State { cost, position }) = heap.pop() {
+        if position == goal {
+            let mut route = vec![goal];
+            let mut current = goal;
+            while let Some(p) = prev[current] {
+                route.push(p);
+                current = p;
+            }
+            route.reverse();
+            return Some((cost, route));
+        }
+
+        if cost > dist[position] {
+            continue;
+        }
+
+        for edge in &adj_list[position] {
+            let next_cost = cost + edge.cost;
+
+            if next_cost < dist[edge.node] {
+                dist[edge.node] = next_cost;
+                prev[edge.node] = Some(position);
+                heap.push(State {
+                    cost: next_cost,
+                    position: edge.node,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Render a `width`-wide cost grid as a string, marking every cell on
+/// `route` with `*` instead of its digit, for debugging which cells an
+/// optimal route actually visits.
+fn format_grid_with_route(grid: &[Vec<usize>], route: &[usize]) -> String {
+    let width = grid[0].len();
+    let on_route: std::collections::HashSet<usize> = route.iter().copied().collect();
+
+    let mut out = String::new();
+    for (i, row) in grid.iter().enumerate() {
+        for (j, cost) in row.iter().enumerate() {
+            if on_route.contains(&(i * width + j)) {
+                out.push('*');
+            } else {
+                out.push_str(&cost.to_string());
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Like `State`, but ordered on `priority` (`cost + heuristic`) instead of
+/// `cost` alone, so the `BinaryHeap` explores the most promising nodes
+/// first. The true `g`-cost is still carried along separately, since that's
+/// what ends up in `dist` and in the final answer.
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct AstarState {
+    priority: usize,
+    cost: usize,
+    position: usize,
+}
+
+impl Ord for AstarState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| self.position.cmp(&other.position))
+    }
+}
+
+impl PartialOrd for AstarState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A* shortest path: the same relaxation as `shortest_path`, but the heap is
+/// ordered on `cost + heuristic(position)` rather than `cost` alone, so the
+/// search is steered towards the goal instead of expanding the frontier
+/// uniformly. `heuristic` must be admissible (never overestimate the true
+/// remaining cost) for the result to stay correct; see
+/// `manhattan_heuristic` for the grid case.
+fn astar(
+    adj_list: &[Vec<Edge>],
+    start: usize,
+    goal: usize,
+    heuristic: impl Fn(usize) -> usize,
+) -> Option<usize> {
+    let mut dist: Vec<_> = (0..adj_list.len()).map(|_| usize::MAX).collect();
+
+    let mut heap = BinaryHeap::new();
+
+    dist[start] = 0;
+    heap.push(AstarState {
+        priority: heuristic(start),
+        cost: 0,
+        position: start,
+    });
+
+    while let Some(AstarState { cost, position, .. }) = heap.pop() {
+        if position == goal {
+            return Some(cost);
+        }
+
+        if cost > dist[position] {
+            continue;
+        }
+
+        for edge in &adj_list[position] {
+            let next_cost = cost + edge.cost;
+
+            if next_cost < dist[edge.node] {
+                dist[edge.node] = next_cost;
+                heap.push(AstarState {
+                    priority: next_cost + heuristic(edge.node),
+                    cost: next_cost,
+                    position: edge.node,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// An admissible heuristic for a `width`-wide grid graph: the Manhattan
+/// distance from node `n` to `goal`, which never overestimates the true
+/// remaining cost since every edge costs at least 1.
+fn manhattan_heuristic(width: usize, goal: usize, n: usize) -> usize {
+    let (gi, gj) = (goal / width, goal % width);
+    let (i, j) = (n / width, n % width);
+    gi.abs_diff(i) + gj.abs_diff(j)
+}
+
+/// The direction a mover last stepped in, for the run-length-constrained
+/// variant of the pathfinder. `None` means "hasn't moved yet", which is
+/// only valid for the starting state: from there, the first step may go any
+/// way, since there's no run to continue or turn out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    None,
+}
+
+impl Direction {
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::None => Direction::None,
+        }
+    }
+
+    fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+            Direction::None => (0, 0),
+        }
+    }
+}
+
+/// A node in the run-length-constrained search: where the mover is, which
+/// way it last stepped, and how many cells it has gone in that direction.
+/// Ordered like `State` (min-heap on `cost`, ties broken on the rest of the
+/// key so `Ord`/`PartialEq` stay consistent).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct ConstrainedState {
+    cost: usize,
+    position: (usize, usize),
+    direction: Direction,
+    run_len: u8,
+}
+
+impl Ord for ConstrainedState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .cmp(&self.cost)
+            .then_with(|| self.position.cmp(&other.position))
+            .then_with(|| (self.direction as u8).cmp(&(other.direction as u8)))
+            .then_with(|| self.run_len.cmp(&other.run_len))
+    }
+}
+
+impl PartialOrd for ConstrainedState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Shortest path on a cost `grid`, but direction-constrained: the mover may
+/// go at most `max_run` cells in a straight line, must go at least
+/// `min_run` cells before turning or stopping, and may never reverse. This
+/// is `shortest_path`'s `min_run = 1, max_run = u8::MAX` case, but
+/// generalized to express crucible-style movement rules where the grid
+/// itself doesn't change.
+///
+/// The state space is keyed on `(position, direction, run_len)` rather than
+/// a flat node index, since the same cell can be the cheapest way in from
+/// several different directions/run-lengths and those aren't
+/// interchangeable for what moves are legal next.
+fn shortest_path_constrained(
+    grid: &[Vec<usize>],
+    start: (usize, usize),
+    goal: (usize, usize),
+    min_run: u8,
+    max_run: u8,
+) -> Option<usize> {
+    let height = grid.len();
+    let width = grid[0].len();
+
+    let mut dist: HashMap<(usize, usize, Direction, u8), usize> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    let start_key = (start.0, start.1, Direction::None, 0);
+    dist.insert(start_key, 0);
+    heap.push(ConstrainedState {
+        cost: 0,
+        position: start,
+        direction: Direction::None,
+        run_len: 0,
+    });
+
+    while let Some(ConstrainedState {
+        cost,
+        position,
+        direction,
+        run_len,
+    }) = heap.pop()
+    {
+        if position == goal && (direction == Direction::None || run_len >= min_run) {
+            return Some(cost);
+        }
+
+        let key = (position.0, position.1, direction, run_len);
+        if cost > dist.get(&key).copied().unwrap_or(usize::MAX) {
+            continue;
+        }
+
+        for next_dir in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            // Never reverse, and only turn (or stop) once the minimum run
+            // has been satisfied.
+            if next_dir == direction.opposite() && direction != Direction::None {
+                continue;
+            }
+            let next_run = if next_dir == direction {
+                run_len + 1
+            } else {
+                if direction != Direction::None && run_len < min_run {
+                    continue;
+                }
+                1
+            };
+            if next_run > max_run {
+                continue;
+            }
+
+            let (di, dj) = next_dir.offset();
+            let ni = position.0 as isize + di;
+            let nj = position.1 as isize + dj;
+            if ni < 0 || nj < 0 || ni as usize >= height || nj as usize >= width {
+                continue;
+            }
+            let (ni, nj) = (ni as usize, nj as usize);
+
+            let next_cost = cost + grid[ni][nj];
+            let next_key = (ni, nj, next_dir, next_run);
+            if next_cost < dist.get(&next_key).copied().unwrap_or(usize::MAX) {
+                dist.insert(next_key, next_cost);
+                heap.push(ConstrainedState {
+                    cost: next_cost,
+                    position: (ni, nj),
+                    direction: next_dir,
+                    run_len: next_run,
+                });
+            }
+        }
+    }
+
+    None
+}
+
 /// Day 15 looks like a simple 'shortest-path' problem.
 /// So lets just try to implement Dijkstra on this.
 
@@ -208,17 +526,134 @@ fn extrapolate(grid: &[Vec<usize>]) -> Vec<Vec<usize>> {
     new_grid
 }
 
+/// A cost grid for graph search that never materializes an explicit
+/// adjacency list: `neighbors` computes the (at most 4) edges out of a node
+/// on demand, from the grid's cost function.
+///
+/// `Tiled` is the part-2 extrapolation without ever allocating the blown-up
+/// grid `extrapolate` builds: a cell's cost is `base[i % h][j % w] + i/h +
+/// j/w`, wrapped into `1..=9`, computed lazily the moment it's asked for.
+enum GridGraph {
+    Direct(Vec<Vec<usize>>),
+    Tiled { base: Vec<Vec<usize>>, factor: usize },
+}
+
+impl GridGraph {
+    fn height(&self) -> usize {
+        match self {
+            GridGraph::Direct(grid) => grid.len(),
+            GridGraph::Tiled { base, factor } => base.len() * factor,
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            GridGraph::Direct(grid) => grid[0].len(),
+            GridGraph::Tiled { base, factor } => base[0].len() * factor,
+        }
+    }
+
+    fn cost(&self, i: usize, j: usize) -> usize {
+        match self {
+            GridGraph::Direct(grid) => grid[i][j],
+            GridGraph::Tiled { base, .. } => {
+                let (h, w) = (base.len(), base[0].len());
+                let value = base[i % h][j % w] + i / h + j / w;
+                (value - 1) % 9 + 1
+            }
+        }
+    }
+
+    /// The (at most 4) edges out of `node`, computed on the fly rather than
+    /// looked up in a stored adjacency list.
+    fn neighbors(&self, node: usize) -> impl Iterator<Item = Edge> + '_ {
+        let (width, height) = (self.width(), self.height());
+        let (i, j) = (node / width, node % width);
+
+        [
+            (i > 0).then(|| Edge {
+                node: (i - 1) * width + j,
+                cost: self.cost(i - 1, j),
+            }),
+            (i + 1 < height).then(|| Edge {
+                node: (i + 1) * width + j,
+                cost: self.cost(i + 1, j),
+            }),
+            (j > 0).then(|| Edge {
+                node: i * width + j - 1,
+                cost: self.cost(i, j - 1),
+            }),
+            (j + 1 < width).then(|| Edge {
+                node: i * width + j + 1,
+                cost: self.cost(i, j + 1),
+            }),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Same relaxation as `shortest_path`, but over a `GridGraph` so the
+/// adjacency list is never built: each node's edges are computed on demand
+/// as the search reaches it.
+fn shortest_path_grid(graph: &GridGraph, start: usize, goal: usize) -> Option<usize> {
+    let mut dist: Vec<_> = (0..graph.width() * graph.height())
+        .map(|_| usize::MAX)
+        .collect();
+
+    let mut heap = BinaryHeap::new();
+
+    dist[start] = 0;
+    heap.push(State {
+        cost: 0,
+        position: start,
+    });
+
+    while let Some(State { cost, position }) = heap.pop() {
+        if position == goal {
+            return Some(cost);
+        }
+
+        if cost > dist[position] {
+            continue;
+        }
+
+        for edge in graph.neighbors(position) {
+            let next_cost = cost + edge.cost;
+
+            if next_cost < dist[edge.node] {
+                dist[edge.node] = next_cost;
+                heap.push(State {
+                    cost: next_cost,
+                    position: edge.node,
+                });
+            }
+        }
+    }
+
+    None
+}
+
 pub fn main() {
-    let graph = read_graph("input/day15.txt");
+    let grid = file_to_grid("input/day15.txt");
+    let width = grid[0].len();
+    let graph = grid_to_graph(&grid);
     let bottom_right = graph.len() - 1;
 
-    let result = shortest_path(&graph, 0, bottom_right);
+    let result = astar(&graph, 0, bottom_right, |n| {
+        manhattan_heuristic(width, bottom_right, n)
+    });
     println!("Part 1: {:?}", result);
 
-    // Part 2:
-    let biggraph = read_graph_p2("input/day15.txt");
-    let bottom_right = biggraph.len() - 1;
-    let result = shortest_path(&biggraph, 0, bottom_right);
+    // Part 2: GridGraph::Tiled computes the extrapolated grid's costs on
+    // demand, so the search runs without ever allocating the 25x-larger
+    // grid extrapolate would build.
+    let big_graph = GridGraph::Tiled {
+        base: grid,
+        factor: 5,
+    };
+    let bottom_right = big_graph.width() * big_graph.height() - 1;
+    let result = shortest_path_grid(&big_graph, 0, bottom_right);
 
     println!("Part 2: {:?}", result);
 }
@@ -288,6 +723,124 @@ mod tests {
         assert_eq!(grid[8][8], 4);
     }
 
+    #[test]
+    fn test_astar_matches_dijkstra() {
+        let graph = read_graph("input/day15_ex.txt");
+        let goal = graph.len() - 1;
+
+        assert_eq!(
+            astar(&graph, 0, goal, |n| manhattan_heuristic(10, goal, n)),
+            shortest_path(&graph, 0, goal)
+        );
+
+        let biggraph = read_graph_p2("input/day15_ex.txt");
+        let goal = biggraph.len() - 1;
+
+        assert_eq!(
+            astar(&biggraph, 0, goal, |n| manhattan_heuristic(50, goal, n)),
+            shortest_path(&biggraph, 0, goal)
+        );
+    }
+
+    #[test]
+    fn test_manhattan_heuristic() {
+        // a 10-wide grid: node 0 is (0,0), node 23 is (2,3)
+        assert_eq!(manhattan_heuristic(10, 0, 23), 5);
+        assert_eq!(manhattan_heuristic(10, 23, 23), 0);
+    }
+
+    #[test]
+    fn test_shortest_path_constrained_matches_unconstrained() {
+        let lines = read_strs("input/day15_ex.txt");
+        let grid: Vec<Vec<usize>> = lines
+            .iter()
+            .map(|l| l.chars().map(|c| c.to_digit(10).unwrap() as usize).collect())
+            .collect();
+        let goal = (grid.len() - 1, grid[0].len() - 1);
+
+        // min_run = 1, max_run = usize::MAX (as far as a u8 can express it)
+        // is the same problem `shortest_path` solves over the adjacency list.
+        let graph = read_graph("input/day15_ex.txt");
+        let flat_goal = graph.len() - 1;
+
+        assert_eq!(
+            shortest_path_constrained(&grid, (0, 0), goal, 1, u8::MAX),
+            shortest_path(&graph, 0, flat_goal)
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_constrained_enforces_min_run() {
+        // A 1x5 grid: only one row, so the mover can only ever go straight
+        // right. Reaching the goal takes a run of 4, but `min_run` of 5
+        // demands the goal only be accepted after at least 5 - which this
+        // grid has no room to do, so the goal is unreachable.
+        let grid = vec![vec![1, 1, 1, 1, 1]];
+        assert_eq!(shortest_path_constrained(&grid, (0, 0), (0, 4), 5, 10), None);
+
+        // relaxing `min_run` back down makes the same grid solvable again.
+        assert_eq!(
+            shortest_path_constrained(&grid, (0, 0), (0, 4), 1, 10),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_with_route() {
+        let graph = read_graph("input/day15_ex.txt");
+        let goal = graph.len() - 1;
+
+        let (cost, route) = shortest_path_with_route(&graph, 0, goal).unwrap();
+        assert_eq!(cost, 40);
+        assert_eq!(cost, shortest_path(&graph, 0, goal).unwrap());
+
+        // the route starts at `start`, ends at `goal`, and every step is a
+        // real edge in the graph.
+        assert_eq!(route.first(), Some(&0));
+        assert_eq!(route.last(), Some(&goal));
+        for pair in route.windows(2) {
+            assert!(graph[pair[0]].iter().any(|e| e.node == pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_format_grid_with_route() {
+        let grid = vec![vec![1, 2], vec![3, 4]];
+        let route = vec![0, 1, 3];
+        assert_eq!(format_grid_with_route(&grid, &route), "**\n3*\n");
+    }
+
+    #[test]
+    fn test_grid_graph_direct_matches_adjacency_list() {
+        let grid = file_to_grid("input/day15_ex.txt");
+        let graph = grid_to_graph(&grid);
+        let goal = graph.len() - 1;
+
+        let grid_graph = GridGraph::Direct(grid);
+        assert_eq!(
+            shortest_path_grid(&grid_graph, 0, goal),
+            shortest_path(&graph, 0, goal)
+        );
+    }
+
+    #[test]
+    fn test_grid_graph_tiled_matches_extrapolated_adjacency_list() {
+        let grid = file_to_grid("input/day15_ex.txt");
+        let big_grid = extrapolate(&grid);
+        let big_graph = grid_to_graph(&big_grid);
+        let goal = big_graph.len() - 1;
+
+        let tiled = GridGraph::Tiled {
+            base: grid,
+            factor: 5,
+        };
+        assert_eq!(
+            shortest_path_grid(&tiled, 0, goal),
+            shortest_path(&big_graph, 0, goal)
+        );
+        assert_eq!(shortest_path_grid(&tiled, 0, goal), Some(315));
+    }
+
     #[test]
     fn test_example_p2() {
         let graph = read_graph_p2("input/day15_ex.txt");
@@ -1,3 +1,6 @@
+use std::collections::VecDeque;
+use std::io::Read;
+
 use aoc2021::read_strs;
 
 /// This task has some quirks that require some special attention.
@@ -26,7 +29,160 @@ enum ParseMode {
     Subpackets(usize),
     SubpacketsInBits(usize),
 }
-///
+
+/// Everything that can go wrong while parsing a BITS transmission, so
+/// malformed input can be handled by a caller instead of aborting the
+/// process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseError {
+    /// The reader ran out of bits before a read completed.
+    UnexpectedEof,
+    /// A character outside `0-9A-Fa-f` showed up in the hex input.
+    InvalidHexChar(char),
+    /// An operator packet's length type ID bit was neither 0 nor 1.
+    UnknownLengthType(u8),
+    /// Bits remained after the top-level packet that weren't zero padding.
+    TrailingData,
+}
+
+/// A source of big-endian bits the parser can pull from, so `Packet`'s
+/// parsing routines don't need to know whether they're reading from a
+/// byte buffer already sitting in memory or being pulled lazily off a
+/// file or socket.
+trait BitSource {
+    /// Consume the next `n` bits (`n` up to 64) as a big-endian value, or
+    /// `None` if fewer than `n` bits remain.
+    fn next_bits(&mut self, n: usize) -> Option<u64>;
+
+    /// How many bits have been consumed so far.
+    fn position(&self) -> usize;
+
+    /// Like `next_bits`, but reports running out of bits as the parser's
+    /// own `ParseError` instead of `None`.
+    fn read(&mut self, n: usize) -> Result<u64, ParseError> {
+        self.next_bits(n).ok_or(ParseError::UnexpectedEof)
+    }
+}
+
+/// A cursor over hex-decoded bytes that reads big-endian bit groups one
+/// bit at a time. Earlier this expanded the whole hex input into a giant
+/// `"0001..."` `String` and re-parsed a `&str` slice with
+/// `from_str_radix` for every read; holding the decoded bytes plus a bit
+/// position instead means parsing only ever allocates the one byte
+/// buffer.
+#[derive(Debug)]
+struct BitReader {
+    bytes: Vec<u8>,
+    cursor: usize,
+}
+
+impl BitReader {
+    /// Decode a hex string into bytes. An odd number of hex digits is
+    /// padded with a trailing zero nibble.
+    fn from_hex(hex: &str) -> Result<BitReader, ParseError> {
+        let mut nibbles = Vec::with_capacity(hex.len());
+        for c in hex.chars() {
+            nibbles.push(c.to_digit(16).ok_or(ParseError::InvalidHexChar(c))? as u8);
+        }
+
+        let mut bytes = Vec::with_capacity(nibbles.len().div_ceil(2));
+        let mut nibbles = nibbles.into_iter();
+        while let Some(hi) = nibbles.next() {
+            let lo = nibbles.next().unwrap_or(0);
+            bytes.push((hi << 4) | lo);
+        }
+
+        Ok(BitReader { bytes, cursor: 0 })
+    }
+
+    /// Whether the cursor has reached the end of the decoded bytes.
+    fn at_end(&self) -> bool {
+        self.cursor >= self.bytes.len() * 8
+    }
+}
+
+impl BitSource for BitReader {
+    fn next_bits(&mut self, n: usize) -> Option<u64> {
+        if self.cursor + n > self.bytes.len() * 8 {
+            return None;
+        }
+
+        let mut value: u64 = 0;
+        for _ in 0..n {
+            let byte = self.bytes[self.cursor / 8];
+            let bit = (byte >> (7 - self.cursor % 8)) & 1;
+            value = (value << 1) | bit as u64;
+            self.cursor += 1;
+        }
+        Some(value)
+    }
+
+    fn position(&self) -> usize {
+        self.cursor
+    }
+}
+
+/// A `BitSource` that pulls hex characters lazily from a `Read`, decoding
+/// them into bits only as the parser asks for them. Unlike `BitReader`,
+/// which needs the whole transmission in memory up front, this lets a
+/// caller decode a packet straight off a file or socket.
+#[allow(dead_code)]
+struct HexStreamSource<R: Read> {
+    reader: R,
+    bits: VecDeque<bool>,
+    position: usize,
+}
+
+#[allow(dead_code)]
+impl<R: Read> HexStreamSource<R> {
+    fn new(reader: R) -> HexStreamSource<R> {
+        HexStreamSource {
+            reader,
+            bits: VecDeque::new(),
+            position: 0,
+        }
+    }
+
+    /// Pull hex characters from the underlying reader, decoding each into
+    /// 4 bits, until at least `n` bits are buffered or the reader runs dry.
+    fn fill(&mut self, n: usize) {
+        let mut byte = [0u8; 1];
+        while self.bits.len() < n {
+            match self.reader.read(&mut byte) {
+                Ok(1) => {
+                    if let Some(digit) = (byte[0] as char).to_digit(16) {
+                        for i in (0..4).rev() {
+                            self.bits.push_back((digit >> i) & 1 == 1);
+                        }
+                    }
+                    // non-hex bytes (e.g. a trailing newline) are skipped
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+impl<R: Read> BitSource for HexStreamSource<R> {
+    fn next_bits(&mut self, n: usize) -> Option<u64> {
+        self.fill(n);
+        if self.bits.len() < n {
+            return None;
+        }
+
+        let mut value: u64 = 0;
+        for _ in 0..n {
+            value = (value << 1) | self.bits.pop_front().unwrap() as u64;
+        }
+        self.position += n;
+        Some(value)
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+}
+
 /// Because we chose Rust, we have to live with the fact that ownership problems
 /// could occur when we pass slices around to recursive calls. Of course we don't need
 /// mutable borrows, so we might just get away with it.
@@ -52,6 +208,22 @@ enum OperatorType {
     EqualTo,
 }
 
+impl OperatorType {
+    /// The 3-bit type ID this operator decodes from (and re-encodes to).
+    #[allow(dead_code)]
+    fn type_id(self) -> u64 {
+        match self {
+            OperatorType::Sum => 0,
+            OperatorType::Product => 1,
+            OperatorType::Minimum => 2,
+            OperatorType::Maximum => 3,
+            OperatorType::GreaterThan => 5,
+            OperatorType::LessThan => 6,
+            OperatorType::EqualTo => 7,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum PacketType {
     LiteralValue(u64),
@@ -69,30 +241,40 @@ struct Packet {
 }
 
 impl Packet {
-    /// lets employ a simple wrapped constructor that can be passed a hex string
-    fn from_hex(hex: &str) -> Packet {
-        let bits = hex_to_binstr(hex);
-        let mut cursor = 0;
-        let packets = Packet::parse(&bits, ParseMode::Single, &mut cursor);
-        // return the first packet in the vector
-        packets.first().unwrap().clone()
+    /// Parse a full transmission from a hex string. This is the entry point
+    /// for untrusted input: it returns `Err` instead of panicking on bad hex,
+    /// a truncated packet, or an unrecognized length type, and additionally
+    /// checks that everything after the top-level packet is zero padding,
+    /// rejecting a hex string that decodes into more than one packet's worth
+    /// of meaningful bits.
+    fn try_from_hex(hex: &str) -> Result<Packet, ParseError> {
+        let mut reader = BitReader::from_hex(hex)?;
+        let packets = Packet::parse(&mut reader, ParseMode::Single)?;
+        let packet = packets
+            .into_iter()
+            .next()
+            .expect("ParseMode::Single always yields a packet");
+
+        while !reader.at_end() {
+            if reader.read(1)? != 0 {
+                return Err(ParseError::TrailingData);
+            }
+        }
+
+        Ok(packet)
     }
 
     /// Here is where the actual parsing should happen.
     /// This function is called for multiple parse modes. Let's figure out the scenarios.
-    /// In any case, we get passed a slice of bits, and a parse mode.
+    /// In any case, we get passed a bit reader, and a parse mode.
     /// In the ParseMode::Single case, we expect a single packet.
     /// In the ParseMode::Subpackets case, we expect n subpackets, so this function
     /// should really return a vector of packets.
-    fn parse(bits: &str, parse_mode: ParseMode, cursor: &mut usize) -> Vec<Packet> {
-        let start_cursor = *cursor;
-
-        println!(
-            "[{} / {}] Start Parsing {:?}",
-            cursor,
-            bits.len(),
-            parse_mode
-        );
+    fn parse<S: BitSource>(
+        source: &mut S,
+        parse_mode: ParseMode,
+    ) -> Result<Vec<Packet>, ParseError> {
+        let start = source.position();
 
         // assume we can just start reading a packet, because there is at least one packet.
         let mut packets = Vec::new();
@@ -100,60 +282,33 @@ impl Packet {
 
         while !done {
             // read one packet and advance the cursor accordingly
-            let packet = Packet::read_packet(bits, cursor);
+            let packet = Packet::read_packet(source)?;
             packets.push(packet);
 
             // check if we are done
-            match parse_mode {
-                ParseMode::Single => {
-                    done = true;
-                }
-                ParseMode::Subpackets(n) => {
-                    done = packets.len() == n;
-                }
-                ParseMode::SubpacketsInBits(n) => {
-                    done = *cursor >= (n + start_cursor);
-                }
-            }
-            println!(
-                "[{} / {}] {:?} {} packets, done: {}",
-                cursor,
-                bits.len(),
-                parse_mode,
-                packets.len(),
-                done
-            );
+            done = match parse_mode {
+                ParseMode::Single => true,
+                ParseMode::Subpackets(n) => packets.len() == n,
+                ParseMode::SubpacketsInBits(n) => source.position() >= start + n,
+            };
         }
 
-        packets
+        Ok(packets)
     }
 
-    /// Reads a single packet from the string, and advances the cursor.
-    fn read_packet(bits: &str, cursor: &mut usize) -> Packet {
-        println!("[{} / {}] Start Reading Packet", cursor, bits.len());
-
+    /// Reads a single packet from the reader, and advances the cursor.
+    fn read_packet<S: BitSource>(reader: &mut S) -> Result<Packet, ParseError> {
         // First we need to read the version and type_id.
-        let version = u8::from_str_radix(&bits[*cursor..*cursor + 3], 2).unwrap();
-        *cursor += 3;
-        let type_id = u8::from_str_radix(&bits[*cursor..*cursor + 3], 2).unwrap();
-        *cursor += 3;
-
-        println!(
-            "[{} / {}] version: {}, type_id: {}",
-            cursor,
-            bits.len(),
-            version,
-            type_id
-        );
+        let version = reader.read(3)? as u8;
+        let type_id = reader.read(3)? as u8;
 
         if type_id == 4 {
             // if the type_id is 4, we have a literal value
-            let value = Packet::read_literal_value(bits, cursor);
-            println!("[{} / {}] literal value: {}", cursor, bits.len(), value);
-            Packet {
+            let value = Packet::read_literal_value(reader)?;
+            Ok(Packet {
                 version,
                 r#type: PacketType::LiteralValue(value),
-            }
+            })
         } else {
             let operator_type = match type_id {
                 0 => OperatorType::Sum,
@@ -163,71 +318,51 @@ impl Packet {
                 5 => OperatorType::GreaterThan,
                 6 => OperatorType::LessThan,
                 7 => OperatorType::EqualTo,
-                _ => panic!("Unknown operator type: {}", type_id),
+                _ => unreachable!("type_id is a 3-bit value and 4 is handled above"),
             };
 
             // if the type_id is different from 4, we have an operator
-            // take the byte at the cursor to determine length type
-            let length_type = bits[*cursor..*cursor + 1].parse::<u8>().unwrap();
-            println!("[{} / {}] length_type: {}", cursor, bits.len(), length_type);
-            *cursor += 1;
+            // read the length type bit
+            let length_type = reader.read(1)?;
             let subpackets = match length_type {
                 0 => {
                     // If the length type ID is 0, then the next 15 bits are a number
                     // that represents the total length in bits of the sub-packets
                     // contained by this packet.
-                    let length = usize::from_str_radix(&bits[*cursor..*cursor + 15], 2).unwrap();
-                    println!(
-                        "[{} / {}] subpackets in {} bits",
-                        cursor,
-                        bits.len(),
-                        length
-                    );
-                    *cursor += 15;
-                    Packet::parse(bits, ParseMode::SubpacketsInBits(length), cursor)
+                    let length = reader.read(15)? as usize;
+                    Packet::parse(reader, ParseMode::SubpacketsInBits(length))?
                 }
                 1 => {
                     // If the length type ID is 1, then the next 11 bits are a number
                     // that represents the number of sub-packets immediately contained
                     // by this packet.
-                    let nr_packets =
-                        usize::from_str_radix(&bits[*cursor..*cursor + 11], 2).unwrap();
-                    println!("[{} / {}] {} subpackets", cursor, bits.len(), nr_packets);
-                    *cursor += 11;
-                    Packet::parse(bits, ParseMode::Subpackets(nr_packets), cursor)
-                }
-                _ => {
-                    panic!("Unknown length type");
+                    let nr_packets = reader.read(11)? as usize;
+                    Packet::parse(reader, ParseMode::Subpackets(nr_packets))?
                 }
+                _ => return Err(ParseError::UnknownLengthType(length_type as u8)),
             };
-            Packet {
+            Ok(Packet {
                 version,
                 r#type: PacketType::Operator((operator_type, subpackets)),
-            }
+            })
         }
     }
 
-    fn read_literal_value(bits: &str, cursor: &mut usize) -> u64 {
-        // create a string to hold the literal value
-        let mut literal_value = String::new();
+    fn read_literal_value<S: BitSource>(reader: &mut S) -> Result<u64, ParseError> {
+        let mut value: u64 = 0;
 
-        while *cursor + 5 <= bits.len() {
-            // take four bits from start + 1 and add these to the literal value
-            literal_value.push_str(&bits[(*cursor + 1)..(*cursor + 5)]);
+        loop {
+            // each group is a continuation bit followed by 4 value bits
+            let group = reader.read(5)?;
+            value = (value << 4) | (group & 0b1111);
 
-            // if the bit at the cursor is a zero, break
-            if bits[*cursor..*cursor + 1].starts_with('0') {
-                // move cursor 5 places over
-                *cursor += 5;
+            // if the continuation bit is zero, this was the last group
+            if group & 0b10000 == 0 {
                 break;
             }
-
-            // otherwise, increment cursor by 5
-            *cursor += 5;
         }
 
-        // return the decimal representation of the binary string literal_value
-        u64::from_str_radix(&literal_value, 2).unwrap()
+        Ok(value)
     }
 
     /// returns the nested total of versions
@@ -282,38 +417,133 @@ impl Packet {
             },
         }
     }
+
+    /// Serialize back to the bit sequence a `BitSource` would read this
+    /// packet from, using length type ID 1 (an 11-bit subpacket count) for
+    /// every operator packet. See `to_bits_with` for total-bit-length
+    /// encoding.
+    #[allow(dead_code)]
+    fn to_bits(&self) -> Vec<bool> {
+        self.to_bits_with(LengthEncoding::SubpacketCount)
+    }
+
+    /// Like `to_bits`, but lets the caller choose how operator packets
+    /// encode their subpacket length.
+    #[allow(dead_code)]
+    fn to_bits_with(&self, length_encoding: LengthEncoding) -> Vec<bool> {
+        let mut bits = Vec::new();
+        push_bits(&mut bits, self.version as u64, 3);
+
+        match &self.r#type {
+            PacketType::LiteralValue(value) => {
+                push_bits(&mut bits, 4, 3);
+                bits.extend(encode_literal_value(*value));
+            }
+            PacketType::Operator((operator_type, subpackets)) => {
+                push_bits(&mut bits, operator_type.type_id(), 3);
+
+                let encoded: Vec<Vec<bool>> = subpackets
+                    .iter()
+                    .map(|p| p.to_bits_with(length_encoding))
+                    .collect();
+
+                match length_encoding {
+                    LengthEncoding::SubpacketCount => {
+                        bits.push(true);
+                        push_bits(&mut bits, subpackets.len() as u64, 11);
+                    }
+                    LengthEncoding::TotalBits => {
+                        bits.push(false);
+                        let total_bits: usize = encoded.iter().map(Vec::len).sum();
+                        push_bits(&mut bits, total_bits as u64, 15);
+                    }
+                }
+
+                for sub_bits in encoded {
+                    bits.extend(sub_bits);
+                }
+            }
+        }
+
+        bits
+    }
+
+    /// Serialize to the hex string `try_from_hex` expects, padding the
+    /// final nibble with zeros the same way a real transmission's trailer
+    /// does.
+    #[allow(dead_code)]
+    fn to_hex(&self) -> String {
+        bits_to_hex(&self.to_bits())
+    }
 }
 
-fn hex_to_binstr(hex: &str) -> String {
-    let mut binstr = String::new();
-    for c in hex.chars() {
-        let bin = match c.to_ascii_uppercase() {
-            '0' => "0000",
-            '1' => "0001",
-            '2' => "0010",
-            '3' => "0011",
-            '4' => "0100",
-            '5' => "0101",
-            '6' => "0110",
-            '7' => "0111",
-            '8' => "1000",
-            '9' => "1001",
-            'A' => "1010",
-            'B' => "1011",
-            'C' => "1100",
-            'D' => "1101",
-            'E' => "1110",
-            'F' => "1111",
-            _ => panic!("Invalid hex character: {}", c),
-        };
-        binstr.push_str(bin);
-    }
-    binstr
+/// Render a bit sequence as an uppercase hex string, padding the final
+/// nibble with zeros.
+#[allow(dead_code)]
+fn bits_to_hex(bits: &[bool]) -> String {
+    let mut bits = bits.to_vec();
+    while bits.len() % 4 != 0 {
+        bits.push(false);
+    }
+
+    bits.chunks(4)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u8, |acc, &bit| (acc << 1) | bit as u8);
+            std::char::from_digit(value as u32, 16)
+                .unwrap()
+                .to_ascii_uppercase()
+        })
+        .collect()
+}
+
+/// Which length-type header an encoded operator packet uses, mirroring the
+/// two length-type IDs `read_packet` understands.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LengthEncoding {
+    /// Length type ID 1: an 11-bit subpacket count. Preferred, since it
+    /// doesn't require knowing the encoded size of the subpackets up front.
+    SubpacketCount,
+    /// Length type ID 0: a 15-bit total bit length.
+    TotalBits,
+}
+
+/// Append the low `n` bits of `value`, most significant bit first.
+#[allow(dead_code)]
+fn push_bits(bits: &mut Vec<bool>, value: u64, n: usize) {
+    for i in (0..n).rev() {
+        bits.push((value >> i) & 1 == 1);
+    }
+}
+
+/// Split a literal value into 4-bit groups (at least one, even for zero),
+/// each prefixed with a continuation bit that's set on every group but the
+/// last.
+#[allow(dead_code)]
+fn encode_literal_value(value: u64) -> Vec<bool> {
+    let mut groups = Vec::new();
+    let mut remaining = value;
+    loop {
+        groups.push(remaining & 0b1111);
+        remaining >>= 4;
+        if remaining == 0 {
+            break;
+        }
+    }
+    groups.reverse();
+
+    let last = groups.len() - 1;
+    let mut bits = Vec::new();
+    for (i, group) in groups.into_iter().enumerate() {
+        bits.push(i != last);
+        push_bits(&mut bits, group, 4);
+    }
+    bits
 }
 
 pub fn main() {
     let lines = read_strs("input/day16.txt");
-    let packet = Packet::from_hex(&lines[0]);
+    let packet = Packet::try_from_hex(&lines[0]).expect("failed to parse packet");
     println!("Version sum: {}", packet.version_sum());
     println!("Expression value: {}", packet.value());
 }
@@ -323,19 +553,36 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_hex_to_binstr() {
-        assert_eq!(hex_to_binstr("D2FE28"), "110100101111111000101000");
+    fn test_bit_reader_read() {
+        let mut reader = BitReader::from_hex("D2FE28").unwrap();
+        assert_eq!(reader.read(4).unwrap(), 0b1101);
+        assert_eq!(reader.read(4).unwrap(), 0b0010);
+        assert_eq!(reader.read(16).unwrap(), 0b1111111000101000);
+    }
+
+    #[test]
+    fn test_bit_reader_invalid_hex_char() {
+        assert_eq!(
+            BitReader::from_hex("D2FG28").unwrap_err(),
+            ParseError::InvalidHexChar('G')
+        );
+    }
+
+    #[test]
+    fn test_bit_reader_unexpected_eof() {
+        let mut reader = BitReader::from_hex("D2").unwrap();
+        assert_eq!(reader.read(9).unwrap_err(), ParseError::UnexpectedEof);
     }
 
     #[test]
     fn test_version() {
-        let packet = Packet::from_hex("D2FE28");
+        let packet = Packet::try_from_hex("D2FE28").unwrap();
         assert_eq!(packet.version, 6);
     }
 
     #[test]
     fn test_literal_value() {
-        let packet = Packet::from_hex("D2FE28");
+        let packet = Packet::try_from_hex("D2FE28").unwrap();
         // match on packet.type
         if let PacketType::LiteralValue(value) = packet.r#type {
             assert_eq!(value, 2021);
@@ -344,23 +591,24 @@ mod tests {
 
     #[test]
     fn test_literal_value_parsing() {
-        let mut cursor: usize = 28;
-        let bits = hex_to_binstr("38006F45291200");
-        let lit_val = Packet::read_literal_value(&bits, &mut cursor);
+        let mut reader = BitReader::from_hex("38006F45291200").unwrap();
+
+        reader.cursor = 28;
+        let lit_val = Packet::read_literal_value(&mut reader).unwrap();
         assert_eq!(lit_val, 10);
         // cursor should be at 33
-        assert_eq!(cursor, 33);
+        assert_eq!(reader.cursor, 33);
 
-        let mut cursor = 39;
-        let lit_val = Packet::read_literal_value(&bits, &mut cursor);
+        reader.cursor = 39;
+        let lit_val = Packet::read_literal_value(&mut reader).unwrap();
         assert_eq!(lit_val, 20);
         // cursor should be at 49
-        assert_eq!(cursor, 49);
+        assert_eq!(reader.cursor, 49);
     }
 
     #[test]
     fn test_operator() {
-        let packet = Packet::from_hex("38006F45291200");
+        let packet = Packet::try_from_hex("38006F45291200").unwrap();
 
         // packet version should be 1
         assert_eq!(packet.version, 1);
@@ -388,7 +636,7 @@ mod tests {
 
     #[test]
     fn test_operator_2() {
-        let packet = Packet::from_hex("EE00D40C823060");
+        let packet = Packet::try_from_hex("EE00D40C823060").unwrap();
 
         // packet version should be 7
         assert_eq!(packet.version, 7);
@@ -423,48 +671,177 @@ mod tests {
 
     #[test]
     fn test_version_sum() {
-        let packet = Packet::from_hex("D2FE28");
+        let packet = Packet::try_from_hex("D2FE28").unwrap();
         assert_eq!(packet.version_sum(), 6);
 
-        let packet = Packet::from_hex("8A004A801A8002F478");
+        let packet = Packet::try_from_hex("8A004A801A8002F478").unwrap();
         assert_eq!(packet.version_sum(), 16);
 
-        let packet = Packet::from_hex("620080001611562C8802118E34");
+        let packet = Packet::try_from_hex("620080001611562C8802118E34").unwrap();
         assert_eq!(packet.version_sum(), 12);
 
-        let packet = Packet::from_hex("C0015000016115A2E0802F182340");
+        let packet = Packet::try_from_hex("C0015000016115A2E0802F182340").unwrap();
         assert_eq!(packet.version_sum(), 23);
 
-        let packet = Packet::from_hex("A0016C880162017C3686B18A3D4780");
+        let packet = Packet::try_from_hex("A0016C880162017C3686B18A3D4780").unwrap();
         assert_eq!(packet.version_sum(), 31);
     }
 
     #[test]
     fn test_expressions() {
-        let packet = Packet::from_hex("C200B40A82");
+        let packet = Packet::try_from_hex("C200B40A82").unwrap();
         assert_eq!(packet.value(), 3);
 
-        let packet = Packet::from_hex("04005AC33890");
+        let packet = Packet::try_from_hex("04005AC33890").unwrap();
         assert_eq!(packet.value(), 54);
 
-        let packet = Packet::from_hex("880086C3E88112");
+        let packet = Packet::try_from_hex("880086C3E88112").unwrap();
         assert_eq!(packet.value(), 7);
 
-        let packet = Packet::from_hex("CE00C43D881120");
+        let packet = Packet::try_from_hex("CE00C43D881120").unwrap();
         assert_eq!(packet.value(), 9);
 
-        let packet = Packet::from_hex("D8005AC2A8F0");
+        let packet = Packet::try_from_hex("D8005AC2A8F0").unwrap();
         assert_eq!(packet.value(), 1);
 
-        let packet = Packet::from_hex("F600BC2D8F");
+        let packet = Packet::try_from_hex("F600BC2D8F").unwrap();
         assert_eq!(packet.value(), 0);
 
         // 9C005AC2F8F0 produces 0, because 5 is not equal to 15.
-        let packet = Packet::from_hex("9C005AC2F8F0");
+        let packet = Packet::try_from_hex("9C005AC2F8F0").unwrap();
         assert_eq!(packet.value(), 0);
 
         // 9C0141080250320F1802104A08 produces 1, because 1 + 3 = 2 * 2.
-        let packet = Packet::from_hex("9C0141080250320F1802104A08");
+        let packet = Packet::try_from_hex("9C0141080250320F1802104A08").unwrap();
         assert_eq!(packet.value(), 1);
     }
+
+    #[test]
+    fn test_try_from_hex_invalid_hex_char() {
+        assert_eq!(
+            Packet::try_from_hex("D2FZ28").unwrap_err(),
+            ParseError::InvalidHexChar('Z')
+        );
+    }
+
+    #[test]
+    fn test_try_from_hex_unexpected_eof() {
+        // a literal-value packet header with no value groups to follow
+        assert_eq!(
+            Packet::try_from_hex("D0").unwrap_err(),
+            ParseError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_try_from_hex_trailing_data() {
+        // D2FE28 is a complete literal-value packet on its own; appending
+        // another non-zero nibble leaves trailing data that isn't padding.
+        assert_eq!(
+            Packet::try_from_hex("D2FE28F0").unwrap_err(),
+            ParseError::TrailingData
+        );
+    }
+
+    #[test]
+    fn test_hex_stream_source_matches_buffered() {
+        // A packet parsed lazily from a `Read` should agree with one
+        // parsed from a fully-buffered `BitReader`.
+        let mut stream =
+            HexStreamSource::new(std::io::Cursor::new(b"620080001611562C8802118E34".to_vec()));
+        let packets = Packet::parse(&mut stream, ParseMode::Single).unwrap();
+        let streamed = packets.into_iter().next().unwrap();
+
+        let mut reader = BitReader::from_hex("620080001611562C8802118E34").unwrap();
+        let buffered = Packet::parse(&mut reader, ParseMode::Single)
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(streamed.version_sum(), buffered.version_sum());
+        assert_eq!(streamed.value(), buffered.value());
+    }
+
+    /// A `BitSource` fed from an explicit bit vector, so `read_packet` can
+    /// be exercised against patterns that don't round-trip through hex.
+    struct BitVecSource {
+        bits: Vec<bool>,
+        position: usize,
+    }
+
+    impl BitVecSource {
+        fn from_str(bits: &str) -> BitVecSource {
+            BitVecSource {
+                bits: bits.chars().map(|c| c == '1').collect(),
+                position: 0,
+            }
+        }
+    }
+
+    impl BitSource for BitVecSource {
+        fn next_bits(&mut self, n: usize) -> Option<u64> {
+            if self.position + n > self.bits.len() {
+                return None;
+            }
+            let mut value: u64 = 0;
+            for _ in 0..n {
+                value = (value << 1) | self.bits[self.position] as u64;
+                self.position += 1;
+            }
+            Some(value)
+        }
+
+        fn position(&self) -> usize {
+            self.position
+        }
+    }
+
+    #[test]
+    fn test_read_packet_from_synthetic_bit_source() {
+        // version 6, type 4 (literal), groups 10111/11110/00101 spell out
+        // the D2FE28 example's literal value of 2021, one bit at a time.
+        let mut source = BitVecSource::from_str("110100101111111000101000");
+        let packet = Packet::read_packet(&mut source).unwrap();
+
+        assert_eq!(packet.version, 6);
+        assert_eq!(packet.value(), 2021);
+    }
+
+    #[test]
+    fn test_to_hex_round_trip() {
+        // every example this file's other tests already decode should
+        // survive a to_hex() / try_from_hex() round trip unchanged.
+        for hex in [
+            "D2FE28",
+            "38006F45291200",
+            "EE00D40C823060",
+            "8A004A801A8002F478",
+            "620080001611562C8802118E34",
+            "C0015000016115A2E0802F182340",
+            "A0016C880162017C3686B18A3D4780",
+            "C200B40A82",
+            "04005AC33890",
+            "880086C3E88112",
+            "CE00C43D881120",
+            "D8005AC2A8F0",
+            "F600BC2D8F",
+            "9C005AC2F8F0",
+            "9C0141080250320F1802104A08",
+        ] {
+            let original = Packet::try_from_hex(hex).unwrap();
+            let round_tripped = Packet::try_from_hex(&original.to_hex()).unwrap();
+
+            assert_eq!(round_tripped.version_sum(), original.version_sum());
+            assert_eq!(round_tripped.value(), original.value());
+        }
+    }
+
+    #[test]
+    fn test_to_bits_with_total_bits_encoding_round_trips() {
+        let original = Packet::try_from_hex("38006F45291200").unwrap();
+        let hex = bits_to_hex(&original.to_bits_with(LengthEncoding::TotalBits));
+
+        let round_tripped = Packet::try_from_hex(&hex).unwrap();
+        assert_eq!(round_tripped.version_sum(), original.version_sum());
+        assert_eq!(round_tripped.value(), original.value());
+    }
 }
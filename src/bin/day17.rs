@@ -1,10 +1,12 @@
+use aoc2021::read_strs;
+
 /// This task is a simulation task. How high can we shoot a probe while still landing
 /// it on the target area.
 ///
 
 /// This struct Vec2 can be used for positions and velocities.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Vec2 {
+pub(crate) struct Vec2 {
     x: i32,
     y: i32,
 }
@@ -60,27 +62,45 @@ impl Probe {
             && self.pos.y <= self.target.1.y
     }
 
+    /// Classify the current position/velocity: still possibly en route, sitting
+    /// inside the target area, or definitively overshot (past the right edge, or
+    /// below the target while still descending, in which case it can never climb
+    /// back up into it).
+    pub fn classify(&self) -> TrajectoryState {
+        if self.on_target() {
+            TrajectoryState::InTargetArea
+        } else if self.pos.x > self.target.1.x || (self.pos.y < self.target.0.y && self.vel.y < 0) {
+            TrajectoryState::Overshot
+        } else {
+            TrajectoryState::EnRoute
+        }
+    }
+
     /// Checks if the probe will land on target area after x steps,
     /// and returns the max_y.
     fn reaches_target(&mut self) -> Option<i32> {
-        while !self.on_target() {
-            self.step();
-            // if the x position is to the right of the target area,
-            // the probe will not reach the target area.
-            if self.pos.x > self.target.1.x {
-                return None;
-            }
-            // if the y position is below the target area,
-            // and the y velocity is negative,
-            // the probe will not reach the target area.
-            if self.pos.y < self.target.0.y && self.vel.y < 0 {
-                return None;
+        loop {
+            match self.classify() {
+                TrajectoryState::InTargetArea => return Some(self.max_y),
+                TrajectoryState::Overshot => return None,
+                TrajectoryState::EnRoute => self.step(),
             }
         }
-        Some(self.max_y)
     }
 }
 
+/// The outcome of classifying a probe's current position and velocity against its
+/// target area, without re-simulating the whole trajectory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrajectoryState {
+    /// Still outside the target area, but not yet known to have missed it.
+    EnRoute,
+    /// Currently sitting inside the target area.
+    InTargetArea,
+    /// Past the target area (to the right) or falling below it: can never land now.
+    Overshot,
+}
+
 /// Quite possibly, finding the best solution that gives the highest max_y is not a
 /// matter of iteratively trying all possible solutions.
 ///
@@ -202,12 +222,11 @@ pub fn find_y_vels(y1: i32, y2: i32) -> Vec<i32> {
 /// combination of velocities will be a valid solution. Since some y velocities will hit
 /// after one step, some x velocities will need more steps to reach the target area.
 /// However, since we have a very finite list of combinations, we can simply check
-/// every combination and report back on the highest y position, which is what we were
-/// looking for initially.
-pub fn find_max_y(x1: i32, x2: i32, y1: i32, y2: i32) -> i32 {
+/// every combination and collect the ones that land in the target area.
+pub(crate) fn find_valid_velocities(x1: i32, x2: i32, y1: i32, y2: i32) -> Vec<Vec2> {
     let x_vels = find_x_vels(x1, x2);
     let y_vels = find_y_vels(y1, y2);
-    let mut max_y = 0;
+    let mut valid = Vec::new();
 
     for x_vel in &x_vels {
         for y_vel in &y_vels {
@@ -221,52 +240,361 @@ pub fn find_max_y(x1: i32, x2: i32, y1: i32, y2: i32) -> i32 {
                 y1,
                 y2,
             );
-            if let Some(my) = probe.reaches_target() {
-                if my > max_y {
-                    max_y = my;
-                }
+            if probe.reaches_target().is_some() {
+                valid.push(Vec2 {
+                    x: *x_vel,
+                    y: *y_vel,
+                });
             }
         }
     }
 
-    max_y
+    valid
+}
+
+/// The highest apex is the largest max_y among all valid velocity pairs, each
+/// re-simulated to pull out its max_y.
+pub fn find_max_y(x1: i32, x2: i32, y1: i32, y2: i32) -> i32 {
+    find_valid_velocities(x1, x2, y1, y2)
+        .into_iter()
+        .map(|vel| {
+            let mut probe = Probe::new(vel, x1, x2, y1, y2);
+            probe.reaches_target().unwrap_or(0)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// When the target's lower y bound is negative, we don't actually need to simulate
+/// anything to find the highest apex. Whatever upwards velocity we pick, the probe
+/// returns to y=0 with a downward velocity one more than its initial upward velocity
+/// (by symmetry of the parabola). The best launch is the fastest downward step that
+/// still lands inside the target on the very next step after crossing y=0, i.e.
+/// `vy = -y1 - 1`. That grazes the bottom edge of the target, and the apex height is
+/// the triangular number `vy*(vy+1)/2`.
+pub fn find_max_y_closed_form(y1: i32) -> i32 {
+    let vy = -y1 - 1;
+    vy * (vy + 1) / 2
 }
 
 /// For step two, we are glad we took the effort to deduct a way to get the distinct
 /// velocities for each axis that reach the target area.
 /// The assignment of part two is to simply count the number of pairs that work
 fn count_valid_values(x1: i32, x2: i32, y1: i32, y2: i32) -> i32 {
-    let x_vels = find_x_vels(x1, x2);
-    let y_vels = find_y_vels(y1, y2);
-    let mut count = 0;
+    find_valid_velocities(x1, x2, y1, y2).len() as i32
+}
 
-    for x_vel in &x_vels {
-        for y_vel in &y_vels {
-            let mut probe = Probe::new(
-                Vec2 {
-                    x: *x_vel,
-                    y: *y_vel,
-                },
-                x1,
-                x2,
-                y1,
-                y2,
-            );
-            if probe.reaches_target().is_some() {
-                count += 1;
-            }
-        }
-    }
-    count
+/// Parse the canonical puzzle format `target area: x=20..30, y=-10..-5` into a
+/// pair of corners, normalized so that `.0` is the min corner and `.1` is the
+/// max corner, matching what `Probe::new` and `on_target` expect.
+pub(crate) fn parse_target_area(input: &str) -> Option<(Vec2, Vec2)> {
+    let rest = input.trim().strip_prefix("target area: ")?;
+    let (x_part, y_part) = rest.split_once(", ")?;
+
+    let (x1, x2) = x_part.strip_prefix("x=")?.split_once("..")?;
+    let (y1, y2) = y_part.strip_prefix("y=")?.split_once("..")?;
+
+    let x1: i32 = x1.parse().ok()?;
+    let x2: i32 = x2.parse().ok()?;
+    let y1: i32 = y1.parse().ok()?;
+    let y2: i32 = y2.parse().ok()?;
+
+    let (x1, x2) = if x1 <= x2 { (x1, x2) } else { (x2, x1) };
+    let (y1, y2) = if y1 <= y2 { (y1, y2) } else { (y2, y1) };
+
+    Some((Vec2 { x: x1, y: y1 }, Vec2 { x: x2, y: y2 }))
 }
 
 pub fn main() {
-    let max_y = find_max_y(257, 286, -101, -57);
+    let lines = read_strs("input/day17.txt");
+    let (min, max) = parse_target_area(&lines[0]).expect("failed to parse target area");
+
+    let max_y = find_max_y(min.x, max.x, min.y, max.y);
     println!("max_y: {}", max_y);
-    let valid_values = count_valid_values(257, 286, -101, -57);
+    let valid_values = count_valid_values(min.x, max.x, min.y, max.y);
     println!("valid_values: {}", valid_values);
 }
 
+/// Optional subsystem: turns the deterministic probe of part one into a
+/// testbed for guided launches under uncertainty. A `NoisyProbe` behaves like
+/// `Probe`, except every step also feels a small random horizontal "wind"
+/// impulse, so the true position can no longer be computed in closed form.
+/// A `ParticleFilter` estimates where the probe actually is from noisy
+/// position measurements.
+///
+/// There's no external RNG crate in this project, so we carry a tiny
+/// self-contained xorshift generator, good enough for sampling wind gusts
+/// and particles without pulling in a dependency for it.
+mod noisy_probe {
+    use super::Vec2;
+
+    /// A small, fast, self-contained PRNG (xorshift64*). Not cryptographic,
+    /// just enough entropy to drive wind sampling and particle resampling.
+    struct Rng {
+        state: u64,
+    }
+
+    impl Rng {
+        fn new(seed: u64) -> Rng {
+            Rng { state: seed.max(1) }
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut x = self.state;
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            self.state = x;
+            x.wrapping_mul(0x2545F4914F6CDD1D)
+        }
+
+        /// Uniform float in [0, 1).
+        fn next_f64(&mut self) -> f64 {
+            (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        /// Standard-normal sample via the Box-Muller transform.
+        fn next_gaussian(&mut self) -> f64 {
+            let u1 = self.next_f64().max(f64::EPSILON);
+            let u2 = self.next_f64();
+            (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+        }
+    }
+
+    /// A probe whose horizontal drag is perturbed every step by a random
+    /// wind impulse drawn from `N(0, wind_std)`, in addition to the usual
+    /// deterministic drag/gravity.
+    pub struct NoisyProbe {
+        pub pos: (f64, f64),
+        pub vel: (f64, f64),
+        wind_std: f64,
+        rng: Rng,
+    }
+
+    impl NoisyProbe {
+        pub fn new(vel: Vec2, wind_std: f64, seed: u64) -> NoisyProbe {
+            NoisyProbe {
+                pos: (0.0, 0.0),
+                vel: (vel.x as f64, vel.y as f64),
+                wind_std,
+                rng: Rng::new(seed),
+            }
+        }
+
+        /// Advance the true (hidden) state by one step.
+        pub fn step(&mut self) {
+            let wind = self.rng.next_gaussian() * self.wind_std;
+            self.pos.0 += self.vel.0;
+            self.pos.1 += self.vel.1;
+            self.vel.0 += match self.vel.0 {
+                x if x > 0.0 => -1.0,
+                x if x < 0.0 => 1.0,
+                _ => 0.0,
+            };
+            self.vel.0 += wind;
+            self.vel.1 -= 1.0;
+        }
+
+        /// Take a noisy position measurement, as an external sensor would.
+        pub fn measure(&mut self, measurement_std: f64) -> (f64, f64) {
+            (
+                self.pos.0 + self.rng.next_gaussian() * measurement_std,
+                self.pos.1 + self.rng.next_gaussian() * measurement_std,
+            )
+        }
+    }
+
+    /// A single particle: a hypothesis about the probe's position, velocity,
+    /// and how likely it is to be right.
+    #[derive(Clone, Copy, Debug)]
+    struct Particle {
+        pos: (f64, f64),
+        vel: (f64, f64),
+        weight: f64,
+    }
+
+    /// Estimates the hidden state of a `NoisyProbe` from noisy measurements
+    /// by maintaining a population of `P` weighted particles.
+    pub struct ParticleFilter {
+        particles: Vec<Particle>,
+        wind_std: f64,
+        last_consistent: (f64, f64),
+        rng: Rng,
+    }
+
+    impl ParticleFilter {
+        /// Spawn `count` particles around the commanded initial velocity.
+        pub fn new(initial_vel: Vec2, wind_std: f64, count: usize, seed: u64) -> ParticleFilter {
+            let weight = 1.0 / count as f64;
+            let particles = vec![
+                Particle {
+                    pos: (0.0, 0.0),
+                    vel: (initial_vel.x as f64, initial_vel.y as f64),
+                    weight,
+                };
+                count
+            ];
+            ParticleFilter {
+                particles,
+                wind_std,
+                last_consistent: (0.0, 0.0),
+                rng: Rng::new(seed),
+            }
+        }
+
+        /// Advance every particle by one control step, each with its own
+        /// independently sampled wind impulse.
+        pub fn predict(&mut self) {
+            for particle in &mut self.particles {
+                let wind = self.rng.next_gaussian() * self.wind_std;
+                particle.pos.0 += particle.vel.0;
+                particle.pos.1 += particle.vel.1;
+                particle.vel.0 += match particle.vel.0 {
+                    x if x > 0.0 => -1.0,
+                    x if x < 0.0 => 1.0,
+                    _ => 0.0,
+                };
+                particle.vel.0 += wind;
+                particle.vel.1 -= 1.0;
+            }
+        }
+
+        /// Incorporate a noisy position measurement: reweight every particle
+        /// by how likely it would be to produce that measurement, then
+        /// resample. Particles whose position is wildly inconsistent with
+        /// the observation (beyond `6` measurement std-devs) are rejected
+        /// outright as hard-inconsistent, rather than merely down-weighted.
+        pub fn update(&mut self, measurement: (f64, f64), measurement_std: f64) {
+            let var = measurement_std * measurement_std;
+            for particle in &mut self.particles {
+                let dx = particle.pos.0 - measurement.0;
+                let dy = particle.pos.1 - measurement.1;
+                let inconsistent =
+                    dx.abs() > 6.0 * measurement_std || dy.abs() > 6.0 * measurement_std;
+                particle.weight *= if inconsistent {
+                    0.0
+                } else {
+                    (-(dx * dx + dy * dy) / (2.0 * var)).exp()
+                };
+            }
+
+            let total_weight: f64 = self.particles.iter().map(|p| p.weight).sum();
+            if total_weight <= 0.0 {
+                // No particle survived: snap the whole population back to the
+                // last consistent observation rather than estimating garbage.
+                let count = self.particles.len();
+                let weight = 1.0 / count as f64;
+                self.particles = vec![
+                    Particle {
+                        pos: self.last_consistent,
+                        vel: (0.0, 0.0),
+                        weight,
+                    };
+                    count
+                ];
+                return;
+            }
+
+            self.last_consistent = measurement;
+            self.resample(total_weight);
+        }
+
+        /// Systematic resampling: draw `P` new particles proportional to
+        /// weight, using a single random offset and evenly spaced pointers
+        /// so the sample is low-variance, then reset all weights to `1/P`.
+        fn resample(&mut self, total_weight: f64) {
+            let count = self.particles.len();
+            let step = total_weight / count as f64;
+            let start = self.rng.next_f64() * step;
+
+            let mut resampled = Vec::with_capacity(count);
+            let mut cumulative = self.particles[0].weight;
+            let mut i = 0;
+            for k in 0..count {
+                let target = start + k as f64 * step;
+                while cumulative < target && i < count - 1 {
+                    i += 1;
+                    cumulative += self.particles[i].weight;
+                }
+                resampled.push(Particle {
+                    pos: self.particles[i].pos,
+                    vel: self.particles[i].vel,
+                    weight: 1.0 / count as f64,
+                });
+            }
+            self.particles = resampled;
+        }
+
+        /// The weighted-mean position/velocity estimate.
+        pub fn estimate(&self) -> ((f64, f64), (f64, f64)) {
+            let total_weight: f64 = self.particles.iter().map(|p| p.weight).sum();
+            if total_weight <= 0.0 {
+                return (self.last_consistent, (0.0, 0.0));
+            }
+            let (mut px, mut py, mut vx, mut vy) = (0.0, 0.0, 0.0, 0.0);
+            for particle in &self.particles {
+                px += particle.pos.0 * particle.weight;
+                py += particle.pos.1 * particle.weight;
+                vx += particle.vel.0 * particle.weight;
+                vy += particle.vel.1 * particle.weight;
+            }
+            (
+                (px / total_weight, py / total_weight),
+                (vx / total_weight, vy / total_weight),
+            )
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        /// the particle filter should track a noisy probe closely enough
+        /// that the estimate stays within a few units of the true position.
+        fn test_particle_filter_tracks_noisy_probe() {
+            let launch = Vec2 { x: 7, y: 9 };
+            let wind_std = 0.2;
+            let measurement_std = 1.0;
+
+            let mut truth = NoisyProbe::new(launch, wind_std, 42);
+            let mut filter = ParticleFilter::new(launch, wind_std, 2000, 1337);
+
+            for _ in 0..10 {
+                truth.step();
+                filter.predict();
+                let measurement = truth.measure(measurement_std);
+                filter.update(measurement, measurement_std);
+            }
+
+            let (est_pos, _est_vel) = filter.estimate();
+            assert!((est_pos.0 - truth.pos.0).abs() < 10.0);
+            assert!((est_pos.1 - truth.pos.1).abs() < 10.0);
+        }
+
+        #[test]
+        /// when every particle is rejected as inconsistent, the filter
+        /// should snap back to the last consistent observation instead of
+        /// producing a nonsensical estimate.
+        fn test_particle_filter_handles_zero_valid_particles() {
+            let launch = Vec2 { x: 7, y: 9 };
+            let mut filter = ParticleFilter::new(launch, 0.2, 100, 7);
+
+            filter.predict();
+            filter.update((7.0, 9.0), 1.0);
+            let (before, _) = filter.estimate();
+
+            // a wildly inconsistent measurement should reject every particle
+            // and fall back to the last consistent estimate.
+            filter.predict();
+            filter.update((1.0e6, -1.0e6), 1.0);
+            let (after, _) = filter.estimate();
+
+            assert_eq!(after, before);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,6 +665,25 @@ mod tests {
         assert_eq!(outcome, Some(45));
     }
 
+    #[test]
+    /// test the trajectory classifier for each of its three outcomes
+    fn test_probe_classify() {
+        let fresh = Probe::new(Vec2 { x: 6, y: 9 }, 20, 30, -10, -5);
+        assert_eq!(fresh.classify(), TrajectoryState::EnRoute);
+
+        let mut on_target = Probe::new(Vec2 { x: 7, y: 2 }, 20, 30, -10, -5);
+        for _ in 1..=7 {
+            on_target.step();
+        }
+        assert_eq!(on_target.classify(), TrajectoryState::InTargetArea);
+
+        let mut overshot = Probe::new(Vec2 { x: 17, y: -4 }, 20, 30, -10, -5);
+        while overshot.classify() == TrajectoryState::EnRoute {
+            overshot.step();
+        }
+        assert_eq!(overshot.classify(), TrajectoryState::Overshot);
+    }
+
     #[test]
     /// test find_x_vels
     fn test_find_x_vels() {
@@ -377,4 +724,41 @@ mod tests {
     fn test_find_max_y() {
         assert_eq!(find_max_y(20, 30, -10, -5), 45);
     }
+
+    #[test]
+    /// the example target area has exactly 112 distinct valid launch velocities
+    fn test_find_valid_velocities() {
+        assert_eq!(find_valid_velocities(20, 30, -10, -5).len(), 112);
+    }
+
+    #[test]
+    /// the closed-form apex computation should agree with the simulating find_max_y
+    fn test_find_max_y_closed_form() {
+        assert_eq!(find_max_y_closed_form(-10), 45);
+        assert_eq!(find_max_y_closed_form(-10), find_max_y(20, 30, -10, -5));
+    }
+
+    #[test]
+    /// test parse_target_area with the canonical puzzle format
+    fn test_parse_target_area() {
+        assert_eq!(
+            parse_target_area("target area: x=20..30, y=-10..-5"),
+            Some((Vec2 { x: 20, y: -10 }, Vec2 { x: 30, y: -5 }))
+        );
+    }
+
+    #[test]
+    /// test parse_target_area normalizes reversed corners
+    fn test_parse_target_area_reversed() {
+        assert_eq!(
+            parse_target_area("target area: x=30..20, y=-5..-10"),
+            Some((Vec2 { x: 20, y: -10 }, Vec2 { x: 30, y: -5 }))
+        );
+    }
+
+    #[test]
+    /// test parse_target_area rejects malformed input
+    fn test_parse_target_area_malformed() {
+        assert_eq!(parse_target_area("not a target area"), None);
+    }
 }
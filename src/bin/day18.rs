@@ -1,7 +1,6 @@
 use std::{fmt::Display, ops::Add, str::FromStr};
 
 use aoc2021::read_strs;
-use itertools::Itertools;
 
 /// Snailfish numbers
 /// -----------------
@@ -9,6 +8,39 @@ use itertools::Itertools;
 /// is a pair - an ordered list of two elements. Each element of the pair can be
 /// either a regular number or another pair.
 
+/// What can go wrong parsing a `Snailfish`/`Element` from a string, so
+/// callers get something actionable back instead of a panic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnailfishParseError {
+    /// The string didn't start and end with a matching `[`/`]`, or had too
+    /// few characters to ever be one.
+    UnbalancedBrackets,
+    /// A pair's two elements weren't separated by a top-level comma.
+    MissingComma,
+    /// A regular number couldn't be parsed as an `i32`.
+    InvalidNumber(String),
+    /// The input ran out of characters before a complete number/pair could
+    /// be read.
+    UnexpectedEnd,
+    /// The brackets balanced out before the end of the string, leaving
+    /// unconsumed characters after what should have been the whole number.
+    TrailingGarbage(String),
+}
+
+impl Display for SnailfishParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SnailfishParseError::UnbalancedBrackets => write!(f, "unbalanced brackets"),
+            SnailfishParseError::MissingComma => write!(f, "missing top-level comma"),
+            SnailfishParseError::InvalidNumber(s) => write!(f, "invalid number: {s}"),
+            SnailfishParseError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            SnailfishParseError::TrailingGarbage(s) => write!(f, "trailing garbage: {s}"),
+        }
+    }
+}
+
+impl std::error::Error for SnailfishParseError {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Element {
     Number(i32),
@@ -17,18 +49,26 @@ pub enum Element {
 
 /// This allows us to read an element from a string.
 impl FromStr for Element {
-    type Err = ();
+    type Err = SnailfishParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // if the string contains a comma, its a pair
+        if s.is_empty() {
+            return Err(SnailfishParseError::UnexpectedEnd);
+        }
+        // if the string contains a comma, its a pair. A leading `-` on a bare
+        // number is not a delimiter, so this still only matches pairs.
         if s.contains(',') {
             // which means we can parse the string as a Snailfish number
             // and return a Pair enum containing the result
             Ok(Element::Pair(Box::new(s.parse::<Snailfish>()?)))
         } else {
             // otherwise, we can parse the string as a regular number
+            // (negative numbers are handled by `i32`'s own `FromStr`)
             // and return a Number enum containing the result
-            Ok(Element::Number(s.parse::<i32>().expect("Not a number")))
+            Ok(Element::Number(
+                s.parse::<i32>()
+                    .map_err(|_| SnailfishParseError::InvalidNumber(s.to_string()))?,
+            ))
         }
     }
 }
@@ -43,21 +83,45 @@ impl Display for Element {
     }
 }
 
+/// A bare `i32` is always a regular number.
+impl From<i32> for Element {
+    fn from(n: i32) -> Self {
+        Element::Number(n)
+    }
+}
+
+/// Puzzle values are small enough to always fit in an `i32`, but callers
+/// working with `i64`s elsewhere don't need to cast by hand first.
+impl From<i64> for Element {
+    fn from(n: i64) -> Self {
+        Element::Number(n as i32)
+    }
+}
+
+/// An already-built `Snailfish` is, of course, a pair.
+impl From<Snailfish> for Element {
+    fn from(s: Snailfish) -> Self {
+        Element::Pair(Box::new(s))
+    }
+}
+
 impl Element {
-    /// Returns the leftmost number in the entire (sub-)tree.
-    fn leftmost_number(&mut self) -> Option<&mut Self> {
-        match self {
-            Element::Number(_) => Some(self),
-            Element::Pair(p) => p.leftmost_number(),
-        }
+    /// A regular number, as an alternative to `Element::Number(n)` or the
+    /// `From<i32>`/`From<i64>` conversions.
+    pub fn regular(n: i32) -> Element {
+        Element::Number(n)
     }
+}
 
-    /// Returns the rightmost number in the entire (sub-)tree.
-    fn rightmost_number(&mut self) -> Option<&mut Self> {
-        match self {
-            Element::Number(_) => Some(self),
-            Element::Pair(p) => p.rightmost_number(),
-        }
+/// Any tuple of two things that are themselves (or convert into) elements is
+/// a pair, so `Snailfish::from((1, (2, 3)))` builds `[1,[2,3]]` directly,
+/// without going through `FromStr`.
+impl<L: Into<Element>, R: Into<Element>> From<(L, R)> for Element {
+    fn from((left, right): (L, R)) -> Self {
+        Element::Pair(Box::new(Snailfish {
+            left: left.into(),
+            right: right.into(),
+        }))
     }
 }
 
@@ -67,11 +131,53 @@ pub struct Snailfish {
     right: Element,
 }
 
+/// A single left/right turn while descending from the root of a snailfish
+/// number to one of its elements. A sequence of these addresses an `Element`
+/// without needing to hold a live reference into the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// One entry of the flattened, left-to-right list of a snailfish number's
+/// regular numbers. `prev`/`next` are indices into that list (`None` at
+/// either end), so explode can look up and update a leaf's neighbour in O(1)
+/// instead of searching the tree for it. `path` records where the leaf lives in the tree, so it can be
+/// addressed again later (e.g. after the list itself has been discarded).
+#[derive(Debug, Clone)]
+struct Leaf {
+    value: i32,
+    path: Vec<Side>,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
 /// We want to be able to read in a snailfish number from a string.
 impl FromStr for Snailfish {
-    type Err = ();
+    type Err = SnailfishParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(SnailfishParseError::UnexpectedEnd);
+        }
+        if s.len() < 2 || !s.starts_with('[') || !s.ends_with(']') {
+            return Err(SnailfishParseError::UnbalancedBrackets);
+        }
+        // the brackets should only balance out once, right at the end; if
+        // they balance out earlier, there's a second top-level value glued
+        // on after the first (e.g. "[1,2][3,4]") rather than one pair.
+        let mut depth = 0;
+        for (i, c) in s.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 && i != s.len() - 1 {
+                return Err(SnailfishParseError::TrailingGarbage(s[i + 1..].to_string()));
+            }
+        }
         // ignore the first and last character
         let s = &s[1..s.len() - 1];
         // walk through the string, counting opening and closing brackets
@@ -105,7 +211,7 @@ impl FromStr for Snailfish {
             Ok(Snailfish { left, right })
         } else {
             // if we didn't find a comma, errr
-            Err(())
+            Err(SnailfishParseError::MissingComma)
         }
     }
 }
@@ -116,6 +222,26 @@ impl Display for Snailfish {
     }
 }
 
+/// Build a pair directly from native data, e.g. `Snailfish::from((1, (2, 3)))`,
+/// instead of going through `FromStr` or nested `Element::Pair(Box::new(...))`
+/// literals.
+impl<L: Into<Element>, R: Into<Element>> From<(L, R)> for Snailfish {
+    fn from((left, right): (L, R)) -> Self {
+        Snailfish {
+            left: left.into(),
+            right: right.into(),
+        }
+    }
+}
+
+impl Snailfish {
+    /// Build a pair directly, as an alternative to `Snailfish::from((l, r))`
+    /// when naming the constructor reads better at the call site.
+    pub fn pair<L: Into<Element>, R: Into<Element>>(left: L, right: R) -> Snailfish {
+        Snailfish::from((left, right))
+    }
+}
+
 /// we want to be able to add two snailfish numbers
 /// so lets get cheeky and implement the add operator
 impl Add for Snailfish {
@@ -132,7 +258,91 @@ impl Add for Snailfish {
     }
 }
 
+/// Drives `Add` through a single left fold, reusing one growing accumulator
+/// rather than allocating a fresh tree per step. Snailfish addition has no
+/// identity element, so summing an empty iterator has no value to return;
+/// `checked_sum` surfaces that as `None` instead of panicking, and the
+/// `Sum`/`Sum<&Snailfish>` impls below build on it, panicking on empty input
+/// the way `std`'s own numeric `Sum` impls do (e.g. `i32`'s, via its `0`
+/// identity) since `sum()` itself has no way to return an `Option`.
+impl Snailfish {
+    pub fn checked_sum<I: IntoIterator<Item = Snailfish>>(iter: I) -> Option<Snailfish> {
+        iter.into_iter().reduce(|a, b| a + b)
+    }
+}
+
+impl std::iter::Sum<Snailfish> for Snailfish {
+    fn sum<I: Iterator<Item = Snailfish>>(iter: I) -> Self {
+        Snailfish::checked_sum(iter).expect("cannot sum an empty list of snailfish numbers")
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Snailfish> for Snailfish {
+    fn sum<I: Iterator<Item = &'a Snailfish>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
 impl Snailfish {
+    /// Recursively collect every regular number in left-to-right order,
+    /// alongside the path of turns from the root that reaches it.
+    fn collect_leaves(&self, path: &mut Vec<Side>, out: &mut Vec<(Vec<Side>, i32)>) {
+        path.push(Side::Left);
+        match &self.left {
+            Element::Number(n) => out.push((path.clone(), *n)),
+            Element::Pair(p) => p.collect_leaves(path, out),
+        }
+        path.pop();
+
+        path.push(Side::Right);
+        match &self.right {
+            Element::Number(n) => out.push((path.clone(), *n)),
+            Element::Pair(p) => p.collect_leaves(path, out),
+        }
+        path.pop();
+    }
+
+    /// Materialize every regular number in this snailfish number into an
+    /// ordered, doubly-linked list of `Leaf`s.
+    fn leaves(&self) -> Vec<Leaf> {
+        let mut raw = vec![];
+        self.collect_leaves(&mut vec![], &mut raw);
+        let len = raw.len();
+        raw.into_iter()
+            .enumerate()
+            .map(|(i, (path, value))| Leaf {
+                value,
+                path,
+                prev: i.checked_sub(1),
+                next: (i + 1 < len).then_some(i + 1),
+            })
+            .collect()
+    }
+
+    /// Iterate over this snailfish number's regular numbers, in left-to-right
+    /// order. Built on the same leaf list `explode` uses, so it's free for
+    /// callers that just want to fold, sum, or validate the regular numbers.
+    pub fn leaf_values(&self) -> impl Iterator<Item = i32> + '_ {
+        self.leaves().into_iter().map(|leaf| leaf.value)
+    }
+
+    /// Look up the `Element` a leaf (or an exploding pair's parent) path
+    /// addresses, following `path` one turn at a time from the root.
+    fn element_at_mut(&mut self, path: &[Side]) -> &mut Element {
+        let (side, rest) = path.split_first().expect("path must not be empty");
+        let element = match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        };
+        if rest.is_empty() {
+            element
+        } else if let Element::Pair(p) = element {
+            p.element_at_mut(rest)
+        } else {
+            unreachable!("path continues past a regular number")
+        }
+    }
+
     /// return true if the number needs reducing
     fn needs_reducing(&self) -> bool {
         // if any pair is nested inside four pairs, we need to reduce (explode)
@@ -218,131 +428,54 @@ impl Snailfish {
     /// the numbers to the left or right of the pair.
     /// We need to return a boolean to indicate if there was an explosion, so we don't
     /// explode two times.
+    /// Explode the first pair nested inside four pairs, if there is one.
+    /// `level`/`neighbours` are no longer needed now that explode is driven
+    /// by the flattened leaf list (see [`Snailfish::leaves`]), but the
+    /// signature is kept so existing callers don't have to change.
     fn explode(
         &mut self,
-        level: usize,
-        neighbours: (Option<&mut Element>, Option<&mut Element>),
+        _level: usize,
+        _neighbours: (Option<&mut Element>, Option<&mut Element>),
     ) -> bool {
-        // The trouble with this algorithm is that it's not only recursive, but
-        // it also bubbles up in two directions, and should bubble down the exploded
-        // pair again.
-        // There's a few assumptions we can make here:
-        // 1. The Snailfish number has a pair that should explode.
-        // 2. We only explode 1 number at a time.
-        // 3. There's optionally one number to the left and optionally one to the right.
-        // So, if we can traverse the tree, and just keep track of references to those
-        // three elements that we need to change, we only need to do one manipulation.
-
-        // keep track of what we exploded so we can replace the arm outside the match
-        // scope.
-        let mut left_exploded = false;
-        let mut right_exploded = false;
-
-        //dbg!(&self.to_string());
-        if level == 4 {
-            // if we have a pair here, do the explosion and addition to the neighbours
-            // and return, because we are done.
-            let mut add_left = 0;
-            let mut add_right = 0;
-
-            // if the left is a pair
-            if let Element::Pair(lp) = &self.left {
-                // if the left pair has a number on the left
-                if let Element::Number(lpl) = lp.left {
-                    add_left = lpl;
-                }
-                // the right term of the pair should be a number
-                if let Element::Number(lpr) = lp.right {
-                    add_right = lpr;
-                }
-
-                // if the left is a pair, the right is either a number or a pair,
-                // so we should get the reference to the leftmost number of that
-                // branch.
-                let neighbour_right = self.right.leftmost_number();
-
-                // add the left, if the left neighbour is a number element
-                if let Some(n) = neighbours.0 {
-                    if let Element::Number(nl) = n {
-                        *n = Element::Number(*nl + add_left);
-                    }
-                }
-
-                // add the right, if the right neighbour is a number element
-                if let Some(n) = neighbour_right {
-                    if let Element::Number(nr) = n {
-                        *n = Element::Number(*nr + add_right);
-                    }
-                }
-
-                left_exploded = true;
-            } else if let Element::Pair(rp) = &self.right {
-                // if the right is a pair
-                if let Element::Number(rpl) = rp.left {
-                    add_left = rpl;
-                }
-                // the left term of the pair should be a number
-                if let Element::Number(rpr) = rp.right {
-                    add_right = rpr;
-                }
-
-                // if the right is a pair, the left is either a number or a pair,
-                // so we should get the reference to the rightmost number of that
-                // branch.
-                let neighbour_right = self.left.rightmost_number();
-
-                // add the left, if the left neighbour is a number element
-                if let Some(n) = neighbour_right {
-                    if let Element::Number(nl) = n {
-                        *n = Element::Number(*nl + add_left);
-                    }
-                }
-
-                // add the right, if the right neighbour is a number element
-                if let Some(n) = neighbours.1 {
-                    if let Element::Number(nr) = n {
-                        *n = Element::Number(*nr + add_right);
-                    }
-                }
-
-                right_exploded = true;
-            }
-
-            if left_exploded {
-                // if we exploded the left, replace the left with a number 0
-                self.left = Element::Number(0);
-            }
-            if right_exploded {
-                // if we exploded the right, replace the right with a number 0
-                self.right = Element::Number(0);
-            }
-
-            left_exploded || right_exploded
-        } else {
-            // if we are not at the fourth level, we need bubble explosion down
-            // to the next level
-
-            // keep track of what exploded, and return if it happened, so we don't explode twice.
-            let mut left_exploded = false;
-            let mut right_exploded = false;
+        let leaves = self.leaves();
+
+        // the pair to explode is the one made of two adjacent leaves that
+        // share the same parent at depth 4 (i.e. their paths agree on the
+        // first four turns, and diverge as Left then Right on the fifth).
+        let target = (0..leaves.len().saturating_sub(1)).find(|&i| {
+            let (a, b) = (&leaves[i], &leaves[i + 1]);
+            a.path.len() == 5
+                && b.path.len() == 5
+                && a.path[..4] == b.path[..4]
+                && a.path[4] == Side::Left
+                && b.path[4] == Side::Right
+        });
+
+        let Some(i) = target else {
+            return false;
+        };
 
-            if let Element::Pair(p) = &mut self.left {
-                left_exploded = p.explode(level + 1, (neighbours.0, self.right.leftmost_number()));
+        // add the exploding pair's left value into the preceding leaf, and
+        // its right value into the following one, each in O(1) thanks to
+        // the leaf list's `prev`/`next` links.
+        if let Some(p) = leaves[i].prev {
+            let path = leaves[p].path.clone();
+            if let Element::Number(v) = self.element_at_mut(&path) {
+                *v += leaves[i].value;
             }
-            if left_exploded {
-                return true;
+        }
+        if let Some(n) = leaves[i + 1].next {
+            let path = leaves[n].path.clone();
+            if let Element::Number(v) = self.element_at_mut(&path) {
+                *v += leaves[i + 1].value;
             }
+        }
 
-            if let Element::Pair(p) = &mut self.right {
-                right_exploded = p.explode(level + 1, (self.left.rightmost_number(), neighbours.1));
-            }
-            if right_exploded {
-                return true;
-            }
+        // collapse the exploded pair itself down to a single `0`.
+        let parent_path = leaves[i].path[..4].to_vec();
+        *self.element_at_mut(&parent_path) = Element::Number(0);
 
-            // nothing exploded below us.
-            false
-        }
+        true
     }
 
     /// Perform a split on the number.
@@ -393,32 +526,6 @@ impl Snailfish {
         false
     }
 
-    fn leftmost_number(&mut self) -> Option<&mut Element> {
-        // if the left is a number, return it
-        if let Element::Number(_) = &self.left {
-            return Some(&mut self.left);
-        }
-        // if the left is a pair, return the leftmost number
-        if let Element::Pair(p) = &mut self.left {
-            return p.leftmost_number();
-        }
-        // if we get here, we have a problem
-        None
-    }
-
-    fn rightmost_number(&mut self) -> Option<&mut Element> {
-        // if the right is a number, return it
-        if let Element::Number(_) = &self.right {
-            return Some(&mut self.right);
-        }
-        // if the right is a pair, return the rightmost number
-        if let Element::Pair(p) = &mut self.right {
-            return p.rightmost_number();
-        }
-        // if we get here, we have a problem
-        None
-    }
-
     fn magnitude(&self) -> i32 {
         // The magnitude of a pair is 3 times the magnitude of its left element plus 2 times the magnitude
         // of its right element. The magnitude of a regular number is just that number.
@@ -434,19 +541,309 @@ impl Snailfish {
     }
 }
 
+/// An alternative, non-recursive representation of a snailfish number: a flat
+/// stream of brackets and values instead of a tree of boxed pairs. Where
+/// `Snailfish::explode` above rebuilds a leaf list and writes back through
+/// per-leaf paths into the `Box<Snailfish>` tree, the flat form turns explode
+/// and split into a single left-to-right scan over a `Vec<Token>`, at the
+/// cost of losing the tree's type-level structure.
+pub mod flat {
+    use std::{fmt::Display, ops::Add, str::FromStr};
+
+    /// One token of a flattened snailfish number, in bracket order.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Token {
+        Open,
+        Close,
+        Number(i32),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FlatSnailfish(Vec<Token>);
+
+    impl FromStr for FlatSnailfish {
+        type Err = ();
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut tokens = vec![];
+            let mut chars = s.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '[' => tokens.push(Token::Open),
+                    ']' => tokens.push(Token::Close),
+                    ',' => {}
+                    '0'..='9' => {
+                        let mut n = c.to_digit(10).unwrap() as i32;
+                        while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                            n = n * 10 + d as i32;
+                            chars.next();
+                        }
+                        tokens.push(Token::Number(n));
+                    }
+                    _ => return Err(()),
+                }
+            }
+            Ok(FlatSnailfish(tokens))
+        }
+    }
+
+    /// Printing a flat token stream still has to reconstruct the comma
+    /// placement, since a `,` only belongs between a pair's two children.
+    /// We track, per open bracket we're currently inside, whether its first
+    /// child has already been written.
+    impl Display for FlatSnailfish {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            let mut first_child = vec![];
+            for t in &self.0 {
+                match t {
+                    Token::Open => {
+                        if first_child.last() == Some(&true) {
+                            write!(f, ",")?;
+                        }
+                        write!(f, "[")?;
+                        first_child.push(false);
+                    }
+                    Token::Close => {
+                        write!(f, "]")?;
+                        first_child.pop();
+                        if let Some(done) = first_child.last_mut() {
+                            *done = true;
+                        }
+                    }
+                    Token::Number(n) => {
+                        if first_child.last() == Some(&true) {
+                            write!(f, ",")?;
+                        }
+                        write!(f, "{}", n)?;
+                        if let Some(done) = first_child.last_mut() {
+                            *done = true;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Add for FlatSnailfish {
+        type Output = FlatSnailfish;
+
+        fn add(self, other: Self) -> Self::Output {
+            let mut tokens = vec![Token::Open];
+            tokens.extend(self.0);
+            tokens.extend(other.0);
+            tokens.push(Token::Close);
+            let mut sf = FlatSnailfish(tokens);
+            sf.reduce();
+            sf
+        }
+    }
+
+    impl FlatSnailfish {
+        /// Explode the first pair nested inside four pairs, if there is one.
+        /// We only need to track bracket depth while scanning left to right:
+        /// the first `Open` that pushes the depth past 4 is guaranteed (by
+        /// the puzzle's invariant that a number is reduced after every step)
+        /// to be directly followed by `Number(a), Number(b), Close`.
+        fn try_explode(&mut self) -> bool {
+            let mut depth = 0;
+            for i in 0..self.0.len() {
+                match self.0[i] {
+                    Token::Open => {
+                        depth += 1;
+                        if depth > 4 {
+                            let (a, b) = match (self.0[i + 1], self.0[i + 2]) {
+                                (Token::Number(a), Token::Number(b)) => (a, b),
+                                _ => unreachable!("a pair past depth 4 must hold two numbers"),
+                            };
+                            if let Some(j) = (0..i).rev().find(|&j| matches!(self.0[j], Token::Number(_))) {
+                                if let Token::Number(v) = &mut self.0[j] {
+                                    *v += a;
+                                }
+                            }
+                            if let Some(j) = (i + 4..self.0.len()).find(|&j| matches!(self.0[j], Token::Number(_))) {
+                                if let Token::Number(v) = &mut self.0[j] {
+                                    *v += b;
+                                }
+                            }
+                            self.0.splice(i..i + 4, [Token::Number(0)]);
+                            return true;
+                        }
+                    }
+                    Token::Close => depth -= 1,
+                    Token::Number(_) => {}
+                }
+            }
+            false
+        }
+
+        /// Split the first regular number that is 10 or greater, if there is one.
+        fn try_split(&mut self) -> bool {
+            let Some(i) = self
+                .0
+                .iter()
+                .position(|t| matches!(t, Token::Number(n) if *n >= 10))
+            else {
+                return false;
+            };
+            let Token::Number(n) = self.0[i] else {
+                unreachable!()
+            };
+            self.0.splice(
+                i..=i,
+                [Token::Open, Token::Number(n / 2), Token::Number((n + 1) / 2), Token::Close],
+            );
+            true
+        }
+
+        /// Repeatedly explode, then split, until neither applies.
+        fn reduce(&mut self) {
+            loop {
+                if self.try_explode() {
+                    continue;
+                }
+                if self.try_split() {
+                    continue;
+                }
+                break;
+            }
+        }
+
+        /// The magnitude of a pair is 3 times the magnitude of its left
+        /// element plus 2 times the magnitude of its right element; the
+        /// magnitude of a regular number is just that number. A small value
+        /// stack computes this in one pass: on `Close`, pop the two most
+        /// recent values and push their weighted sum.
+        pub fn magnitude(&self) -> i32 {
+            let mut stack: Vec<i32> = vec![];
+            for t in &self.0 {
+                match t {
+                    Token::Number(n) => stack.push(*n),
+                    Token::Close => {
+                        let right = stack.pop().unwrap();
+                        let left = stack.pop().unwrap();
+                        stack.push(3 * left + 2 * right);
+                    }
+                    Token::Open => {}
+                }
+            }
+            stack.pop().unwrap()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_flat_fromstr_display() {
+            for s in ["[1,2]", "[[1,2],[3,4]]", "[[[[1,2],3],4],5]"] {
+                let n: FlatSnailfish = s.parse().unwrap();
+                assert_eq!(n.to_string(), s);
+            }
+        }
+
+        #[test]
+        fn test_flat_explode() {
+            let cases = [
+                ("[[[[[9,8],1],2],3],4]", "[[[[0,9],2],3],4]"),
+                ("[7,[6,[5,[4,[3,2]]]]]", "[7,[6,[5,[7,0]]]]"),
+                (
+                    "[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]",
+                    "[[3,[2,[8,0]]],[9,[5,[4,[3,2]]]]]",
+                ),
+            ];
+            for (input, expected) in cases {
+                let mut n: FlatSnailfish = input.parse().unwrap();
+                assert!(n.try_explode());
+                assert_eq!(n.to_string(), expected);
+            }
+        }
+
+        #[test]
+        fn test_flat_split() {
+            let mut n: FlatSnailfish = "[10,0]".parse().unwrap();
+            assert!(n.try_split());
+            assert_eq!(n.to_string(), "[[5,5],0]");
+        }
+
+        #[test]
+        fn test_flat_add_and_reduce() {
+            let n1: FlatSnailfish = "[[[[4,3],4],4],[7,[[8,4],9]]]".parse().unwrap();
+            let n2: FlatSnailfish = "[1,1]".parse().unwrap();
+            let sum = n1 + n2;
+            assert_eq!(sum.to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+        }
+
+        #[test]
+        fn test_flat_magnitude() {
+            let n: FlatSnailfish = "[[9,1],[1,9]]".parse().unwrap();
+            assert_eq!(n.magnitude(), 129);
+
+            let n: FlatSnailfish = "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]"
+                .parse()
+                .unwrap();
+            assert_eq!(n.magnitude(), 3488);
+        }
+
+        #[test]
+        fn test_flat_homework_assignment() {
+            let sum = [
+                "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]",
+                "[[[5,[2,8]],4],[5,[[9,9],0]]]",
+                "[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]",
+                "[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]",
+                "[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]",
+                "[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]",
+                "[[[[5,4],[7,7]],8],[[8,3],8]]",
+                "[[9,3],[[9,9],[6,[4,9]]]]",
+                "[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]",
+                "[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]",
+            ]
+            .iter()
+            .map(|s| s.parse::<FlatSnailfish>().unwrap())
+            .reduce(|a, i| a + i)
+            .unwrap();
+
+            assert_eq!(
+                sum.to_string(),
+                "[[[[6,6],[7,6]],[[7,7],[7,0]]],[[[7,7],[7,7]],[[7,8],[9,9]]]]"
+            );
+            assert_eq!(sum.magnitude(), 4140);
+        }
+    }
+}
+
+
+/// Find the largest magnitude reachable by adding any two *different*
+/// numbers from `nrs`, in either order (snailfish addition isn't
+/// commutative, so both `a+b` and `b+a` are candidates). Rather than
+/// building every ordered pair of `Snailfish` values up front (which clones
+/// each one into the pair before it's even evaluated), we permute cheap
+/// `usize` indices and only clone the two operands a pair actually needs,
+/// right at the point we add them.
+///
+/// With the `parallel` feature enabled, pairs are evaluated across threads
+/// via rayon; otherwise the same indexed evaluation runs sequentially.
+/// Whichever path is compiled in is checked against a plain sequential scan
+/// in `test_largest_magnitude_parallel_matches_sequential`.
 pub fn largest_magnitude(nrs: &[Snailfish]) -> i32 {
-    // find the largest magnitude of any number in the array
-    let magnitude = 0;
-    let magnitudes: Vec<i32> = nrs
-        .iter()
-        .permutations(2)
-        .map(|sv| {
-            let sum = sv[0].clone() + sv[1].clone();
-            sum.magnitude()
-        })
+    let n = nrs.len();
+    let pairs: Vec<(usize, usize)> = (0..n)
+        .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
         .collect();
 
-    *magnitudes.iter().max().unwrap_or(&magnitude)
+    let magnitude_of = |&(i, j): &(usize, usize)| (nrs[i].clone() + nrs[j].clone()).magnitude();
+
+    #[cfg(feature = "parallel")]
+    let best = {
+        use rayon::prelude::*;
+        pairs.par_iter().map(magnitude_of).max()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let best = pairs.iter().map(magnitude_of).max();
+
+    best.unwrap_or(0)
 }
 
 pub fn main() {
@@ -503,6 +900,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_tuples() {
+        let number = Snailfish::from((1, (2, 3)));
+        assert_eq!(number.to_string(), "[1,[2,3]]");
+
+        let number = Snailfish::from(((1, 2), (3, 4)));
+        assert_eq!(number.to_string(), "[[1,2],[3,4]]");
+    }
+
+    #[test]
+    fn test_builder_functions() {
+        let number = Snailfish::pair(Element::regular(1), Snailfish::pair(2, 3));
+        assert_eq!(number.to_string(), "[1,[2,3]]");
+
+        let number = Snailfish::pair(1_i64, 2_i64);
+        assert_eq!(number.to_string(), "[1,2]");
+    }
+
+    #[test]
+    fn test_leaf_values() {
+        let number = Snailfish::from_str("[[1,2],[[3,4],5]]").unwrap();
+        let values: Vec<i32> = number.leaf_values().collect();
+        assert_eq!(values, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_fromstr_negative_numbers() {
+        let number: Snailfish = "[-3,[4,-5]]".parse().unwrap();
+        assert_eq!(number.left, Element::Number(-3));
+        if let Element::Pair(pair) = number.right {
+            assert_eq!(pair.left, Element::Number(4));
+            assert_eq!(pair.right, Element::Number(-5));
+        } else {
+            panic!("Right element is not a pair");
+        }
+    }
+
+    #[test]
+    fn test_fromstr_errors() {
+        assert_eq!(
+            "".parse::<Snailfish>(),
+            Err(SnailfishParseError::UnexpectedEnd)
+        );
+        assert_eq!(
+            "[1,2".parse::<Snailfish>(),
+            Err(SnailfishParseError::UnbalancedBrackets)
+        );
+        assert_eq!(
+            "[1 2]".parse::<Snailfish>(),
+            Err(SnailfishParseError::MissingComma)
+        );
+        assert_eq!(
+            "[x,2]".parse::<Snailfish>(),
+            Err(SnailfishParseError::InvalidNumber("x".to_string()))
+        );
+        assert_eq!(
+            "[1,2][3,4]".parse::<Snailfish>(),
+            Err(SnailfishParseError::TrailingGarbage("[3,4]".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_fromstr_multidigit_numbers_roundtrip() {
+        let str = "[-123,[4567,-89]]";
+        let number: Snailfish = str.parse().unwrap();
+        assert_eq!(number.to_string(), str);
+    }
+
     #[test]
     /// test parsing and displaying a snailfish number
     fn test_snailfish_display() {
@@ -713,6 +1178,25 @@ mod tests {
         assert_eq!(number.to_string(), "[[[[5,0],[7,4]],[5,5]],[6,6]]");
     }
 
+    #[test]
+    fn test_sum_matches_manual_fold() {
+        let numbers: Vec<Snailfish> = vec!["[1,1]", "[2,2]", "[3,3]", "[4,4]", "[5,5]", "[6,6]"]
+            .into_iter()
+            .map(|s| Snailfish::from_str(s).unwrap())
+            .collect();
+
+        let summed: Snailfish = numbers.iter().sum();
+        assert_eq!(summed.to_string(), "[[[[5,0],[7,4]],[5,5]],[6,6]]");
+
+        let summed: Snailfish = numbers.into_iter().sum();
+        assert_eq!(summed.to_string(), "[[[[5,0],[7,4]],[5,5]],[6,6]]");
+    }
+
+    #[test]
+    fn test_checked_sum_empty_is_none() {
+        assert_eq!(Snailfish::checked_sum(Vec::new()), None);
+    }
+
     #[test]
     fn a_slightly_larger_example() {
         let sum = vec![
@@ -875,4 +1359,30 @@ mod tests {
 
         assert_eq!(largest_magnitude(&nrs), 3993);
     }
+
+    #[test]
+    fn test_largest_magnitude_parallel_matches_sequential() {
+        // Whichever evaluation strategy `largest_magnitude` is built with
+        // (sequential, or `par_iter` under the `parallel` feature), it's
+        // just evaluating the same ordered-pair index set, so it must agree
+        // with a plain sequential scan over those same pairs.
+        let nrs = vec![
+            "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]",
+            "[[[5,[2,8]],4],[5,[[9,9],0]]]",
+            "[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]",
+            "[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]",
+        ]
+        .iter()
+        .map(|s| Snailfish::from_str(s).unwrap())
+        .collect::<Vec<Snailfish>>();
+
+        let n = nrs.len();
+        let sequential_max = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| (nrs[i].clone() + nrs[j].clone()).magnitude())
+            .max()
+            .unwrap();
+
+        assert_eq!(largest_magnitude(&nrs), sequential_max);
+    }
 }
@@ -1,7 +1,8 @@
 // use vecdeque
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
+use nalgebra::Matrix3;
 
 /// Lets try this again with a different approach.
 /// And the nalgebra library.
@@ -11,7 +12,7 @@ use itertools::Itertools;
 // allow unused variables for now
 #[allow(unused_variables)]
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct Vector3 {
+pub(crate) struct Vector3 {
     x: i32,
     y: i32,
     z: i32,
@@ -50,17 +51,88 @@ impl std::ops::Sub for Vector3 {
     }
 }
 
-#[rustfmt::skip]
-const ROTATIONS: [(i32, i32, i32); 48] = [
-    (1, 2, 3), (1, 3, 2), (2, 1, 3), (2, 3, 1), (3, 1, 2), (3, 2, 1),
-    (1, 2, -3), (1, 3, -2), (2, 1, -3), (2, 3, -1), (3, 1, -2), (3, 2, -1),
-    (1, -2, 3), (1, -3, 2), (2, -1, 3), (2, -3, 1), (3, -1, 2), (3, -2, 1),
-    (1, -2, -3), (1, -3, -2), (2, -1, -3), (2, -3, -1), (3, -1, -2), (3, -2, -1),
-    (-1, 2, 3), (-1, 3, 2), (-2, 1, 3), (-2, 3, 1), (-3, 1, 2), (-3, 2, 1),
-    (-1, 2, -3), (-1, 3, -2), (-2, 1, -3), (-2, 3, -1), (-3, 1, -2), (-3, 2, -1),
-    (-1, -2, 3), (-1, -3, 2), (-2, -1, 3), (-2, -3, 1), (-3, -1, 2), (-3, -2, 1),
-    (-1, -2, -3), (-1, -3, -2), (-2, -1, -3), (-2, -3, -1), (-3, -1, -2), (-3, -2, -1),
-];
+/// Determinant of a 3x3 matrix, computed by hand since nalgebra's
+/// `Matrix::determinant` requires a field (float) scalar type, not `i32`.
+fn determinant3(m: &Matrix3<i32>) -> i32 {
+    m[(0, 0)] * (m[(1, 1)] * m[(2, 2)] - m[(1, 2)] * m[(2, 1)])
+        - m[(0, 1)] * (m[(1, 0)] * m[(2, 2)] - m[(1, 2)] * m[(2, 0)])
+        + m[(0, 2)] * (m[(1, 0)] * m[(2, 1)] - m[(1, 1)] * m[(2, 0)])
+}
+
+/// A proper (orientation-preserving) rotation of 3D space, backed by an
+/// integer rotation matrix. Scanners report beacons in their own, unknown
+/// orientation; recovering which `Rotation` lines a scanner up with the
+/// reference frame is most of this puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Rotation(Matrix3<i32>);
+
+impl Rotation {
+    fn identity() -> Rotation {
+        Rotation(Matrix3::identity())
+    }
+
+    /// The 24 proper rotations of the cube, generated rather than typed out:
+    /// starting from the identity, repeatedly compose with a 90-degree turn
+    /// about the z axis and a 90-degree roll about the x axis, and collect
+    /// matrices until composing with either generator produces nothing new.
+    /// Reflections (determinant -1) can't arise this way since both generators
+    /// have determinant +1 and determinant is multiplicative, but they're
+    /// filtered out anyway as a defensive check.
+    fn all() -> [Rotation; 24] {
+        #[rustfmt::skip]
+        let turn_z = Matrix3::new(
+            0, -1, 0,
+            1, 0, 0,
+            0, 0, 1,
+        );
+        #[rustfmt::skip]
+        let roll_x = Matrix3::new(
+            1, 0, 0,
+            0, 0, -1,
+            0, 1, 0,
+        );
+        let generators = [turn_z, roll_x];
+
+        let mut group = vec![Matrix3::identity()];
+        loop {
+            let mut found_new = false;
+            for m in group.clone() {
+                for g in &generators {
+                    let candidate = g * m;
+                    if !group.contains(&candidate) {
+                        group.push(candidate);
+                        found_new = true;
+                    }
+                }
+            }
+            if !found_new {
+                break;
+            }
+        }
+
+        group.retain(|m| determinant3(m) == 1);
+        let rotations: Vec<Rotation> = group.into_iter().map(Rotation).collect();
+        rotations
+            .try_into()
+            .unwrap_or_else(|v: Vec<Rotation>| panic!("expected 24 proper rotations, got {}", v.len()))
+    }
+
+    /// Applies this rotation to `v` as a matrix-vector product.
+    fn apply(&self, v: Vector3) -> Vector3 {
+        let m = &self.0;
+        Vector3::new(
+            m[(0, 0)] * v.x + m[(0, 1)] * v.y + m[(0, 2)] * v.z,
+            m[(1, 0)] * v.x + m[(1, 1)] * v.y + m[(1, 2)] * v.z,
+            m[(2, 0)] * v.x + m[(2, 1)] * v.y + m[(2, 2)] * v.z,
+        )
+    }
+
+    /// Undoes `apply`. Rotation matrices are orthogonal, so the inverse is
+    /// just the transpose.
+    fn inverse(&self) -> Rotation {
+        Rotation(self.0.transpose())
+    }
+}
 
 impl Vector3 {
     fn new(x: i32, y: i32, z: i32) -> Vector3 {
@@ -71,10 +143,6 @@ impl Vector3 {
         Vector3 { x: 0, y: 0, z: 0 }
     }
 
-    fn default_rotation() -> Vector3 {
-        Vector3 { x: 1, y: 2, z: 3 }
-    }
-
     fn magnitude_squared(&self) -> i32 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
@@ -91,121 +159,10 @@ impl Vector3 {
         (self.x - other.x).abs() + (self.y - other.y).abs() + (self.z - other.z).abs()
     }
 
-    fn get_axis(&self, axis: i32) -> i32 {
-        match axis {
-            1 => self.x,
-            2 => self.y,
-            3 => self.z,
-            -1 => -self.x,
-            -2 => -self.y,
-            -3 => -self.z,
-            _ => panic!("Invalid axis"),
-        }
-    }
-
-    fn from_tuple(tuple: (i32, i32, i32)) -> Vector3 {
-        Vector3 {
-            x: tuple.0,
-            y: tuple.1,
-            z: tuple.2,
-        }
-    }
-
-    fn rotate_tuple(&self, tuple: (i32, i32, i32)) -> Vector3 {
-        Vector3::from_tuple((
-            self.get_axis(tuple.0),
-            self.get_axis(tuple.1),
-            self.get_axis(tuple.2),
-        ))
-    }
-
-    fn rotate(&self, rot: &Vector3) -> Vector3 {
-        Vector3::new(
-            self.get_axis(rot.x),
-            self.get_axis(rot.y),
-            self.get_axis(rot.z),
-        )
-    }
-
-    fn inverse_rotate(&self, rot: &Vector3) -> Vector3 {
-        let mut base = Vector3::zero();
-        // the first item of rot determines where the x is lead from
-        match rot.x {
-            1 => {
-                base.x = self.x;
-            }
-            2 => {
-                base.y = self.x;
-            }
-            3 => {
-                base.z = self.x;
-            }
-            -1 => {
-                base.x = -self.x;
-            }
-            -2 => {
-                base.y = -self.x;
-            }
-            -3 => {
-                base.z = -self.x;
-            }
-            _ => panic!("Invalid axis"),
-        }
-        // the second item of rot determines where the y is lead from
-        match rot.y {
-            1 => {
-                base.x = self.y;
-            }
-            2 => {
-                base.y = self.y;
-            }
-            3 => {
-                base.z = self.y;
-            }
-            -1 => {
-                base.x = -self.y;
-            }
-            -2 => {
-                base.y = -self.y;
-            }
-            -3 => {
-                base.z = -self.y;
-            }
-            _ => panic!("Invalid axis"),
-        }
-        match rot.z {
-            1 => {
-                base.x = self.z;
-            }
-            2 => {
-                base.y = self.z;
-            }
-            3 => {
-                base.z = self.z;
-            }
-            -1 => {
-                base.x = -self.z;
-            }
-            -2 => {
-                base.y = -self.z;
-            }
-            -3 => {
-                base.z = -self.z;
-            }
-            _ => panic!("Invalid axis"),
-        }
-        // the third item of rot determines where the z is lead from
-        // return base
-        base
-    }
-
-    fn inverse_rotate_tuple(&self, tuple: (i32, i32, i32)) -> Vector3 {
-        self.inverse_rotate(&Vector3::from_tuple(tuple))
-    }
 }
 
 #[derive(Debug, Clone, Copy)]
-struct Beacon {
+pub(crate) struct Beacon {
     // The position of the beacon.
     pos: Vector3,
     // The distances to the two closest neighbors.
@@ -214,12 +171,40 @@ struct Beacon {
     close_dist: (Option<i32>, Option<i32>),
 }
 
+/// The multiset of squared pairwise beacon distances for a scanner, sorted
+/// ascending so `shared` can count common distances by merging instead of
+/// hashing. A plain `HashSet` would collapse duplicate distances and
+/// undercount how many beacons two scanners actually have in common.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BeaconFingerprint(Vec<i64>);
+
+impl BeaconFingerprint {
+    /// How many distances (counted with multiplicity) `self` and `other`
+    /// have in common.
+    fn shared(&self, other: &BeaconFingerprint) -> usize {
+        let (mut i, mut j) = (0, 0);
+        let mut count = 0;
+        while i < self.0.len() && j < other.0.len() {
+            match self.0[i].cmp(&other.0[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    count += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
-struct Scanner {
+pub(crate) struct Scanner {
     nr: usize,
     pos: Option<Vector3>,
-    rot: Option<Vector3>,
+    rot: Option<Rotation>,
     beacons: Vec<Beacon>,
 }
 
@@ -227,7 +212,7 @@ impl Scanner {
     pub fn local_to_world_pos(&self, p: Vector3) -> Vector3 {
         // de-rotate the pos
         let p = if let Some(rot) = self.rot {
-            p.inverse_rotate(&rot)
+            rot.inverse().apply(p)
         } else {
             p
         };
@@ -248,7 +233,75 @@ impl Scanner {
             .iter()
             .map(|b| {
                 // beacon absolute position is the beacon position plus the scanner position
-                b.pos.inverse_rotate(&self.rot.unwrap()) + self.pos.unwrap()
+                self.rot.unwrap().inverse().apply(b.pos) + self.pos.unwrap()
+            })
+            .collect()
+    }
+
+    /// The multiset of squared distances between every pair of this
+    /// scanner's beacons. Two scanners that share >= 12 beacons must share
+    /// >= C(12, 2) = 66 identical pairwise distances, so this is a cheap
+    /// pre-filter to run before attempting a full rotation search.
+    pub fn fingerprint(&self) -> BeaconFingerprint {
+        let mut distances: Vec<i64> = Vec::new();
+        for i in 0..self.beacons.len() {
+            for j in (i + 1)..self.beacons.len() {
+                let squared_dist = (self.beacons[i].pos - self.beacons[j].pos).magnitude_squared();
+                distances.push(squared_dist as i64);
+            }
+        }
+        distances.sort_unstable();
+        BeaconFingerprint(distances)
+    }
+
+    /// Whether this scanner and `other` plausibly see >= 12 of the same
+    /// beacons, judged by how many pairwise distances their fingerprints
+    /// have in common. Necessary, not sufficient: the full matcher still
+    /// has to run afterward to confirm and recover the actual rotation.
+    pub fn overlaps(&self, other: &Scanner) -> bool {
+        self.fingerprint().shared(&other.fingerprint()) >= 66
+    }
+
+    /// For each beacon, the set of squared distances from it to every other
+    /// beacon in the same scanner. More robust than `close_dist` for
+    /// recovering beacon correspondences, since it isn't thrown off by
+    /// beacons whose two nearest neighbors happen to be equidistant.
+    fn beacon_distance_sets(&self) -> Vec<HashSet<i32>> {
+        self.beacons
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                self.beacons
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, other)| (b.pos - other.pos).magnitude_squared())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Alternative to `distance_based_matching_beacons` that matches beacons
+    /// by how many pairwise distances they share with a candidate, rather
+    /// than by comparing only the two closest neighbors: two beacons
+    /// correspond when their distance sets share >= 11 values.
+    pub fn fingerprint_matching_beacons(
+        &self,
+        ref_scanner: &Scanner,
+    ) -> Vec<(Beacon, Beacon, Vector3)> {
+        let self_sets = self.beacon_distance_sets();
+        let ref_sets = ref_scanner.beacon_distance_sets();
+
+        self.beacons
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &b)| {
+                ref_scanner
+                    .beacons
+                    .iter()
+                    .enumerate()
+                    .find(|&(j, _)| self_sets[i].intersection(&ref_sets[j]).count() >= 11)
+                    .map(|(_, &ref_b)| (b, ref_b, ref_scanner.local_to_world_pos(ref_b.pos)))
             })
             .collect()
     }
@@ -278,7 +331,12 @@ impl Scanner {
         });
     }
 
-    pub fn likely_rotation_and_pos(&self, ref_scanner: &Scanner) -> Option<(Vector3, Vector3)> {
+    pub fn likely_rotation_and_pos(&self, ref_scanner: &Scanner) -> Option<(Rotation, Vector3)> {
+        // cheap fingerprint pre-filter before doing any rotation search
+        if !self.overlaps(ref_scanner) {
+            return None;
+        }
+
         let matching_beacons = distance_based_matching_beacons(self, ref_scanner);
         // what else do we want to know about this scanner, compared to the reference scanner?
         // if the number of matching beacons is 12, We know that these two scanners see a lot of the same beacons.
@@ -287,17 +345,17 @@ impl Scanner {
         }
 
         // Now that we have matching beacon pairs, we can try to find the position of the scanner.
-        let likely_rotations = ROTATIONS.iter().filter_map(|&r| {
+        let likely_rotations = Rotation::all().into_iter().filter_map(|r| {
             // iterate over the matching pairs to find the position of the scanner based on this rotation
             let unique_positions = matching_beacons
                 .iter()
-                .map(|&(b, _, p)| p - b.pos.inverse_rotate_tuple(r))
+                .map(|&(b, _, p)| p - r.inverse().apply(b.pos))
                 .dedup()
                 .collect::<Vec<_>>();
 
             // if there is only one unique position, we can use this as the position of the scanner
             if unique_positions.len() == 1 {
-                Some((Vector3::from_tuple(r), unique_positions[0]))
+                Some((r, unique_positions[0]))
             } else {
                 None
             }
@@ -311,6 +369,45 @@ impl Scanner {
         }
         None
     }
+
+    /// Alternative to `likely_rotation_and_pos` that doesn't depend on
+    /// `close_dist`, which is fragile when beacons are near-equidistant.
+    ///
+    /// For each of the 24 proper rotations, rotate all of this scanner's
+    /// beacons into the candidate frame, then vote on the offset between
+    /// every reference beacon and every rotated candidate beacon. If 12 or
+    /// more pairs agree on the same offset, the scanners overlap under that
+    /// rotation at that offset. O(24 * |ref| * |self|), but fully
+    /// self-contained.
+    pub fn align_by_offset_voting(&self, ref_scanner: &Scanner) -> Option<(Rotation, Vector3)> {
+        let ref_positions: Vec<Vector3> = ref_scanner
+            .beacons
+            .iter()
+            .map(|b| ref_scanner.local_to_world_pos(b.pos))
+            .collect();
+
+        for r in Rotation::all() {
+            let rotated: Vec<Vector3> = self
+                .beacons
+                .iter()
+                .map(|b| r.inverse().apply(b.pos))
+                .collect();
+
+            let mut votes: HashMap<Vector3, u32> = HashMap::new();
+            for &a in &ref_positions {
+                for &candidate in &rotated {
+                    *votes.entry(a - candidate).or_insert(0) += 1;
+                }
+            }
+
+            if let Some((&delta, _)) = votes.iter().max_by_key(|&(_, &count)| count) {
+                if votes[&delta] >= 12 {
+                    return Some((r, delta));
+                }
+            }
+        }
+        None
+    }
 }
 
 fn distance_based_matching_beacons(
@@ -362,69 +459,126 @@ fn parse_scanner(input: &str) -> Scanner {
     }
 }
 
-fn read_scanners(input: &str) -> Vec<Scanner> {
+pub(crate) fn read_scanners(input: &str) -> Vec<Scanner> {
     input.split("\n\n").map(parse_scanner).collect()
 }
 
-fn main() {
-    let mut scanners = read_scanners(include_str!("../../input/day19.txt"));
-
-    // set the first scanner to 0,0,0 and 1,2,3 as reference
-    scanners[0].pos = Some(Vector3::zero());
-    scanners[0].rot = Some(Vector3::new(1, 2, 3));
+/// Assembles a set of scanners into a single frame of reference.
+///
+/// Scanners start out `unaligned`. The first scanner becomes the reference
+/// frame (position zero, identity rotation) and is placed in
+/// `pending_neighbor_check`; `align_all` then repeatedly pops a pending
+/// scanner and tries to align every still-`unaligned` scanner against it,
+/// moving successes into `pending_neighbor_check` in turn and the
+/// exhausted reference into `aligned`, so an already-aligned pair is never
+/// re-tested.
+#[allow(dead_code)]
+struct ScannerCloud {
+    aligned: Vec<Scanner>,
+    pending_neighbor_check: Vec<Scanner>,
+    unaligned: Vec<Scanner>,
+}
 
-    scanners.iter_mut().for_each(|s| s.distance_calc());
+impl ScannerCloud {
+    fn new(mut scanners: Vec<Scanner>) -> ScannerCloud {
+        scanners.iter_mut().for_each(|s| s.distance_calc());
 
-    // put the ref_scanner in the ref_queue
-    let mut ref_queue: VecDeque<Scanner> = VecDeque::new();
-    ref_queue.push_back(scanners[0].clone());
+        let mut reference = scanners.remove(0);
+        reference.pos = Some(Vector3::zero());
+        reference.rot = Some(Rotation::identity());
 
-    // as long as there are scanners left that don't have an initialized pos
-    while scanners.iter().any(|s| s.pos.is_none()) {
-        // if the ref_queue is empty, panic, because we can't solve this
-        if ref_queue.is_empty() {
-            panic!("No reference scanners found");
+        ScannerCloud {
+            aligned: Vec::new(),
+            pending_neighbor_check: vec![reference],
+            unaligned: scanners,
         }
+    }
 
-        // get the first scanner from the ref_queue
-        let ref_scanner = ref_queue.pop_front().unwrap();
-
-        // loop through the scanners
-        scanners
-            .iter_mut()
-            .filter(|s| s.pos.is_none())
-            .for_each(|s| {
-                // get likely rotation and pos for the scanner
-                if let Some((rot, pos)) = s.likely_rotation_and_pos(&ref_scanner) {
-                    // set the pos and rot of the scanner
+    /// Aligns every scanner against the growing reference frame. Panics if
+    /// some scanners can't be connected to the rest through any chain of
+    /// overlaps.
+    fn align_all(&mut self) {
+        while let Some(reference) = self.pending_neighbor_check.pop() {
+            let candidates = std::mem::take(&mut self.unaligned);
+            for mut s in candidates {
+                // fall back to offset voting when the distance-fingerprint
+                // matcher can't find a unique position (e.g. too many
+                // equidistant beacons for `close_dist` to disambiguate)
+                let rot_pos = s
+                    .likely_rotation_and_pos(&reference)
+                    .or_else(|| s.align_by_offset_voting(&reference));
+                if let Some((rot, pos)) = rot_pos {
                     s.pos = Some(pos);
                     s.rot = Some(rot);
-                    // this scanner can now be used as a reference scanner
-                    ref_queue.push_back(s.clone());
-                    println!(
-                        "S{} >>> REF S{} -> {:?}, {:?}",
-                        s.nr, ref_scanner.nr, s.pos, s.rot
-                    );
+                    println!("S{} >>> REF S{} -> {:?}, {:?}", s.nr, reference.nr, pos, rot);
+                    self.pending_neighbor_check.push(s);
+                } else {
+                    self.unaligned.push(s);
                 }
-            });
+            }
+            self.aligned.push(reference);
+        }
+        assert!(
+            self.unaligned.is_empty(),
+            "could not align every scanner to the reference frame"
+        );
     }
-    // All scanners have positions and rotations now.
-    // We should now build up a list of beacons with absolute positions (relative to reference scanner).
-    // And deduplicate this list. This will tell us how many beacons there truly are.
-    let mut beacons: HashSet<Vector3> = HashSet::new();
-    for scanner in scanners.iter() {
-        beacons.extend(scanner.corrected_beacon_positions());
+
+    /// All beacon positions, deduplicated, relative to the reference frame.
+    fn combine_beacons(&self) -> HashSet<Vector3> {
+        self.aligned
+            .iter()
+            .flat_map(|s| s.corrected_beacon_positions())
+            .collect()
     }
-    // print the number of beacons:
-    println!("Nr of beacons: {}", beacons.len());
 
-    // part 2: largest manhattan distance
-    let distances = scanners
-        .iter()
-        .combinations(2)
-        .map(|c| c[0].world_pos().distance(&c[1].world_pos()));
+    /// The full solve: every deduplicated beacon position and every
+    /// scanner's position, both relative to the reference frame.
+    fn reconstruction(&self) -> Reconstruction {
+        Reconstruction {
+            beacons: self.combine_beacons(),
+            scanner_positions: self.aligned.iter().map(|s| s.world_pos()).collect(),
+        }
+    }
+}
 
-    println!("Largest manhattan distance: {}", distances.max().unwrap());
+/// The result of fully aligning a scanner cloud: every unique beacon and
+/// every scanner's position, both relative to scanner 0's frame of
+/// reference. Bundling both together means a single alignment pass can
+/// answer both halves of the puzzle instead of re-running the matcher.
+pub(crate) struct Reconstruction {
+    pub(crate) beacons: HashSet<Vector3>,
+    pub(crate) scanner_positions: Vec<Vector3>,
+}
+
+impl Reconstruction {
+    /// The largest Manhattan distance between any two scanners.
+    pub fn largest_scanner_distance(&self) -> i64 {
+        self.scanner_positions
+            .iter()
+            .combinations(2)
+            .map(|c| c[0].distance(c[1]) as i64)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Parses and fully solves a scanner cloud, producing the `Reconstruction`
+/// that both puzzle parts read from.
+pub(crate) fn solve(input: &str) -> Reconstruction {
+    let mut cloud = ScannerCloud::new(read_scanners(input));
+    cloud.align_all();
+    cloud.reconstruction()
+}
+
+fn main() {
+    let reconstruction = solve(include_str!("../../input/day19.txt"));
+
+    println!("Nr of beacons: {}", reconstruction.beacons.len());
+    println!(
+        "Largest manhattan distance: {}",
+        reconstruction.largest_scanner_distance()
+    );
 }
 
 #[cfg(test)]
@@ -472,7 +626,7 @@ mod tests {
         let mut scanner = Scanner {
             nr: 0,
             pos: Some(Vector3::zero()),
-            rot: Some(Vector3::new(0, 0, 0)),
+            rot: Some(Rotation::identity()),
             beacons: Vec::new(),
         };
         scanner.beacons.push(Beacon {
@@ -498,6 +652,191 @@ mod tests {
         assert_eq!(scanner.beacons[3].close_dist, (Some(225), Some(229)));
     }
 
+    #[test]
+    fn test_align_by_offset_voting_recovers_rotation_and_offset() {
+        let absolute_positions: Vec<Vector3> =
+            (0..12).map(|i| Vector3::new(i * 10, i * i, -i)).collect();
+
+        let ref_scanner = Scanner {
+            nr: 0,
+            pos: Some(Vector3::zero()),
+            rot: Some(Rotation::identity()),
+            beacons: absolute_positions
+                .iter()
+                .map(|&p| Beacon {
+                    pos: p,
+                    close_dist: (None, None),
+                })
+                .collect(),
+        };
+
+        // a proper rotation: x <- y, y <- z, z <- x
+        #[rustfmt::skip]
+        let rotation = Rotation(Matrix3::new(
+            0, 1, 0,
+            0, 0, 1,
+            1, 0, 0,
+        ));
+        let offset = Vector3::new(100, -50, 20);
+        // local = (absolute - offset) rotated into the scanner's own frame,
+        // i.e. the inverse of what align_by_offset_voting un-rotates by.
+        let beacons: Vec<Beacon> = absolute_positions
+            .iter()
+            .map(|&p| Beacon {
+                pos: rotation.apply(p - offset),
+                close_dist: (None, None),
+            })
+            .collect();
+        let scanner = Scanner {
+            nr: 1,
+            pos: None,
+            rot: None,
+            beacons,
+        };
+
+        let result = scanner.align_by_offset_voting(&ref_scanner);
+        assert!(result.is_some());
+        let (rot, pos) = result.unwrap();
+        assert_eq!(rot, rotation);
+        assert_eq!(pos, offset);
+    }
+
+    #[test]
+    fn test_fingerprint_and_overlaps() {
+        let mut ref_scanner = Scanner {
+            nr: 0,
+            pos: Some(Vector3::zero()),
+            rot: Some(Rotation::identity()),
+            beacons: (0..12)
+                .map(|i| Beacon {
+                    pos: Vector3::new(i * 10, i * i, -i),
+                    close_dist: (None, None),
+                })
+                .collect(),
+        };
+        let mut other = ref_scanner.clone();
+        other.nr = 1;
+
+        // an identical scanner shares every pairwise distance with itself
+        assert_eq!(ref_scanner.fingerprint(), other.fingerprint());
+        assert!(ref_scanner.overlaps(&other));
+
+        // a scanner with a disjoint set of beacons shares none
+        let unrelated = Scanner {
+            nr: 2,
+            pos: None,
+            rot: None,
+            beacons: vec![Beacon {
+                pos: Vector3::new(1_000_000, 0, 0),
+                close_dist: (None, None),
+            }],
+        };
+        assert!(!ref_scanner.overlaps(&unrelated));
+
+        ref_scanner.distance_calc();
+        other.distance_calc();
+    }
+
+    #[test]
+    fn test_fingerprint_shared_counts_duplicate_distances() {
+        // both fingerprints contain the distance 4 twice: a HashSet-based
+        // intersection would only count it once, undercounting the overlap.
+        let a = BeaconFingerprint(vec![1, 4, 4, 9]);
+        let b = BeaconFingerprint(vec![4, 4, 9, 16]);
+        assert_eq!(a.shared(&b), 3);
+    }
+
+    #[test]
+    fn test_fingerprint_matching_beacons() {
+        let absolute_positions: Vec<Vector3> =
+            (0..12).map(|i| Vector3::new(i * 10, i * i, -i)).collect();
+
+        let ref_scanner = Scanner {
+            nr: 0,
+            pos: Some(Vector3::zero()),
+            rot: Some(Rotation::identity()),
+            beacons: absolute_positions
+                .iter()
+                .map(|&p| Beacon {
+                    pos: p,
+                    close_dist: (None, None),
+                })
+                .collect(),
+        };
+        // seen from this scanner, the same beacons sit at the same relative
+        // positions to one another, just translated
+        let offset = Vector3::new(50, 50, 50);
+        let scanner = Scanner {
+            nr: 1,
+            pos: None,
+            rot: None,
+            beacons: absolute_positions
+                .iter()
+                .map(|&p| Beacon {
+                    pos: p - offset,
+                    close_dist: (None, None),
+                })
+                .collect(),
+        };
+
+        let matches = scanner.fingerprint_matching_beacons(&ref_scanner);
+        assert_eq!(matches.len(), 12);
+        for (b, _, world_pos) in &matches {
+            assert_eq!(*world_pos, b.pos + offset);
+        }
+    }
+
+    #[test]
+    fn test_scanner_cloud_aligns_and_combines() {
+        let absolute_positions: Vec<Vector3> =
+            (0..12).map(|i| Vector3::new(i * 10, i * i, -i)).collect();
+
+        let scanner0 = Scanner {
+            nr: 0,
+            pos: None,
+            rot: None,
+            beacons: absolute_positions
+                .iter()
+                .map(|&p| Beacon {
+                    pos: p,
+                    close_dist: (None, None),
+                })
+                .collect(),
+        };
+
+        #[rustfmt::skip]
+        let rotation = Rotation(Matrix3::new(
+            0, 1, 0,
+            0, 0, 1,
+            1, 0, 0,
+        ));
+        let offset = Vector3::new(100, -50, 20);
+        let scanner1 = Scanner {
+            nr: 1,
+            pos: None,
+            rot: None,
+            beacons: absolute_positions
+                .iter()
+                .map(|&p| Beacon {
+                    pos: rotation.apply(p - offset),
+                    close_dist: (None, None),
+                })
+                .collect(),
+        };
+
+        let mut cloud = ScannerCloud::new(vec![scanner0, scanner1]);
+        cloud.align_all();
+
+        // both scanners see the same 12 beacons, so they should combine
+        // into a single deduplicated set
+        let reconstruction = cloud.reconstruction();
+        assert_eq!(reconstruction.beacons.len(), 12);
+        assert_eq!(
+            reconstruction.largest_scanner_distance(),
+            Vector3::zero().distance(&offset) as i64
+        );
+    }
+
     #[test]
     fn determine_neighbours() {
         let input = r#"--- scanner 0 ---
@@ -535,18 +874,47 @@ mod tests {
         assert_eq!(scanners[0].beacons[2].close_dist, (Some(9), Some(25)));
     }
 
+    #[test]
+    fn test_proper_rotations_are_exactly_the_cube_rotation_group() {
+        let proper = Rotation::all();
+        assert_eq!(proper.len(), 24);
+        for r in &proper {
+            assert_eq!(determinant3(&r.0), 1);
+        }
+        // the group is closed: composing any two elements stays in the group
+        for &a in &proper {
+            for &b in &proper {
+                assert!(proper.contains(&Rotation(a.0 * b.0)));
+            }
+        }
+
+        assert!(proper.contains(&Rotation::identity()));
+        // a plain axis swap (no sign flip) is a reflection, not a rotation
+        #[rustfmt::skip]
+        let axis_swap = Matrix3::new(
+            0, 1, 0,
+            1, 0, 0,
+            0, 0, 1,
+        );
+        assert_eq!(determinant3(&axis_swap), -1);
+        assert!(!proper.contains(&Rotation(axis_swap)));
+    }
+
     #[test]
     fn invert_rotate() {
         let v1 = Vector3::new(10, 20, 30);
 
-        assert_eq!(
-            v1.rotate(&Vector3::default_rotation()),
-            Vector3::new(10, 20, 30)
-        );
+        assert_eq!(Rotation::identity().apply(v1), Vector3::new(10, 20, 30));
 
-        let rotated = v1.rotate(&Vector3::new(2, 3, 1));
+        #[rustfmt::skip]
+        let rotation = Rotation(Matrix3::new(
+            0, 1, 0,
+            0, 0, 1,
+            1, 0, 0,
+        ));
+        let rotated = rotation.apply(v1);
         assert_eq!(rotated, Vector3::new(20, 30, 10));
-        let inv_rot = rotated.inverse_rotate(&Vector3::new(2, 3, 1));
+        let inv_rot = rotation.inverse().apply(rotated);
         assert_eq!(inv_rot, v1);
     }
 
@@ -563,7 +931,7 @@ mod tests {
 
         // init first scanner on position (0, 0, 0) and rotation (1, 2, 3)
         scanners[0].pos = Some(Vector3::new(0, 0, 0));
-        scanners[0].rot = Some(Vector3::new(1, 2, 3));
+        scanners[0].rot = Some(Rotation::identity());
 
         // ----------------------------------------------
         // check matching beacons of overlapping scanners
@@ -1,22 +1,52 @@
+use anyhow::{bail, Context, Result};
+use std::env;
 use std::io::BufRead;
 use std::{fs::File, io::BufReader};
 
+#[path = "../days/day10.rs"]
+#[allow(dead_code)]
+mod day10;
+#[path = "../days/day6.rs"]
+#[allow(dead_code)]
+mod day6;
+
+/// Pulled in alongside `day10` above so its `use super::read_strs;` has
+/// something to resolve to when the module is compiled as part of this
+/// binary instead of `src/days/mod.rs`.
+fn read_strs(file_path: &str) -> Vec<String> {
+    let file = File::open(file_path).expect("file not found");
+    let reader = BufReader::new(file);
+    reader
+        .lines()
+        .map(|l| l.expect("failed to parse line"))
+        .collect()
+}
+
 enum Command {
     Forward(i32),
     Down(i32),
     Up(i32),
 }
 
-impl From<&str> for Command {
-    fn from(s: &str) -> Self {
-        // split on space
+impl TryFrom<&str> for Command {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
         let parts = s.split(' ').collect::<Vec<&str>>();
-        if parts[0] == "forward" {
-            Command::Forward(parts[1].parse().unwrap())
-        } else if parts[0] == "down" {
-            Command::Down(parts[1].parse().unwrap())
-        } else {
-            Command::Up(parts[1].parse().unwrap())
+        let op = *parts
+            .first()
+            .with_context(|| format!("empty command line: {:?}", s))?;
+        let arg = parts
+            .get(1)
+            .with_context(|| format!("missing operand in command: {:?}", s))?
+            .parse::<i32>()
+            .with_context(|| format!("unparseable operand in command: {:?}", s))?;
+
+        match op {
+            "forward" => Ok(Command::Forward(arg)),
+            "down" => Ok(Command::Down(arg)),
+            "up" => Ok(Command::Up(arg)),
+            _ => bail!("unknown command: {:?}", op),
         }
     }
 }
@@ -57,35 +87,129 @@ impl Position {
     }
 }
 
-fn read_commands(file_path: &str) -> Vec<Command> {
-    let file = File::open(file_path).expect("file not found");
+fn read_commands(file_path: &str) -> Result<Vec<Command>> {
+    let file = File::open(file_path).with_context(|| format!("failed to open {}", file_path))?;
     let reader = BufReader::new(file);
     reader
         .lines()
-        .map(|l| l.expect("failed to parse line"))
-        .map(|l| l.as_str().into())
+        .enumerate()
+        .map(|(i, l)| {
+            let line = l.with_context(|| format!("failed to read {}:{}", file_path, i + 1))?;
+            Command::try_from(line.as_str())
+                .with_context(|| format!("failed to parse {}:{}", file_path, i + 1))
+        })
         .collect()
 }
 
-fn day2() {
-    let commands = read_commands("input/day2.txt");
+fn final_position(commands: &[Command]) -> Position {
     let mut pos = Position::new();
     for command in commands {
-        pos.move_command(&command);
+        pos.move_command(command);
     }
-    println!("{:?}, distance: {}", pos, pos.distance());
+    pos
 }
 
-fn day2b() {
-    let commands = read_commands("input/day2.txt");
+fn final_position2(commands: &[Command]) -> Position {
     let mut pos = Position::new();
     for command in commands {
-        pos.move_command2(&command);
+        pos.move_command2(command);
+    }
+    pos
+}
+
+fn day2() -> Result<String> {
+    let commands = read_commands("input/day2.txt")?;
+    let pos = final_position(&commands);
+    Ok(format!("{:?}, distance: {}", pos, pos.distance()))
+}
+
+fn day2b() -> Result<String> {
+    let commands = read_commands("input/day2.txt")?;
+    let pos = final_position2(&commands);
+    Ok(format!("{:?}, distance: {}", pos, pos.distance()))
+}
+
+fn day2_all() -> Result<String> {
+    Ok(format!("part1: {}\npart2: {}", day2()?, day2b()?))
+}
+
+fn day10_all() -> Result<String> {
+    Ok(format!(
+        "part1: {}\npart2: {}",
+        day10::day10()?,
+        day10::day10b()?
+    ))
+}
+
+type DayFunc = fn() -> Result<String>;
+
+const DAYS: &[(u32, DayFunc)] = &[(2, day2_all), (6, day6::day6_all), (10, day10_all)];
+
+/// Runs either the single day given as the first CLI argument, or every
+/// registered day in order when none is given, printing a `dayN:` header
+/// before each result and wrapping any error with which day produced it.
+fn run(days: &[(u32, DayFunc)]) {
+    match env::args().nth(1).map(|arg| arg.parse::<u32>()) {
+        Some(Ok(day)) => match days.iter().find(|(n, _)| *n == day) {
+            Some((n, f)) => report(*n, f()),
+            None => eprintln!("no solution registered for day {}", day),
+        },
+        Some(Err(_)) => eprintln!("day must be a number"),
+        None => {
+            for &(n, f) in days {
+                report(n, f());
+            }
+        }
+    }
+}
+
+fn report(day: u32, result: Result<String>) {
+    println!("day{}:", day);
+    match result {
+        Ok(s) => println!("{}", s),
+        Err(e) => eprintln!("error running day {}: {:#}", day, e),
     }
-    println!("{:?}, distance: {}", pos, pos.distance());
 }
 
 fn main() {
-    day2();
-    day2b();
-}
\ No newline at end of file
+    run(DAYS);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_commands() -> Vec<Command> {
+        [
+            "forward 5",
+            "down 5",
+            "forward 8",
+            "up 3",
+            "down 8",
+            "forward 2",
+        ]
+        .iter()
+        .map(|s| Command::try_from(*s).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_final_position_matches_known_distance() {
+        assert_eq!(final_position(&example_commands()).distance(), 150);
+    }
+
+    #[test]
+    fn test_final_position2_matches_known_distance() {
+        assert_eq!(final_position2(&example_commands()).distance(), 900);
+    }
+
+    #[test]
+    fn test_command_try_from_rejects_missing_operand() {
+        assert!(Command::try_from("forward").is_err());
+    }
+
+    #[test]
+    fn test_command_try_from_rejects_unknown_command() {
+        assert!(Command::try_from("sideways 5").is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use aoc2021::automaton::{InfiniteGrid, Rule};
+
 /// Our input is an enhancement algorithm string, and an image.
 /// The enhancement algorithm is interpreted as a 512-length array of bits.
 type Algorithm = [bool; 512];
@@ -33,42 +35,6 @@ fn read_image(txt: &str) -> Image {
     image
 }
 
-fn add_margin(image: &Image, margin: usize) -> Image {
-    // determine the size of the source image
-    let (width, height) = (image[0].len(), image.len());
-    // determine the size of the new image
-    let new_width = width + 2 * margin;
-    let new_height = height + 2 * margin;
-    // create the new image
-    let mut new_image = vec![vec![false; new_width]; new_height];
-    // copy the old image into the new image, centered
-    for y in 0..height {
-        for x in 0..width {
-            new_image[y + margin][x + margin] = image[y][x];
-        }
-    }
-    // return new_image
-    new_image
-}
-
-fn remove_margin(image: &Image, margin: usize) -> Image {
-    // determine the size of the source image
-    let (width, height) = (image[0].len(), image.len());
-    // determine the size of the new image
-    let new_width = width - 2 * margin;
-    let new_height = height - 2 * margin;
-    // create the new image
-    let mut new_image = vec![vec![false; new_width]; new_height];
-    // copy the old image into the new image, centered
-    for y in 0..new_height {
-        for x in 0..new_width {
-            new_image[y][x] = image[y + margin][x + margin];
-        }
-    }
-    // return new_image
-    new_image
-}
-
 fn read_input(s: &str) -> (Algorithm, Image) {
     // split the string on the empty line
     let (a, i) = s.split_once("\n\n").unwrap();
@@ -77,50 +43,43 @@ fn read_input(s: &str) -> (Algorithm, Image) {
     (algo, image)
 }
 
-/// The mask value is determined by reading the nine pixels around and including the current pixel.
-/// So the pixels from x-1,y-1 to x+1,y+1 are read.
-/// If we read outside the image, consider those pixels to be 0.
-fn mask_value(image: &Image, x: i32, y: i32) -> usize {
-    let mut value = 0;
-    for yy in y - 1..y + 2 {
-        for xx in x - 1..x + 2 {
-            if xx >= 0 && xx < image[0].len() as i32 && yy >= 0 && yy < image.len() as i32 {
-                // count the index in in the 3x3 square we are reading
-                let index = (yy - y + 1) * 3 + (xx - x + 1);
-                value += if image[yy as usize][xx as usize] {
-                    1 << (8 - index)
-                } else {
-                    0
-                };
-            }
-        }
-    }
-    value as usize
+/// Day 20's enhancement algorithm, expressed as a `Rule` for the generic
+/// `InfiniteGrid` automaton engine: the next value of a pixel is looked up
+/// in the 512-entry table by treating its 3x3 neighborhood as a 9-bit index,
+/// and the infinite background flips to whatever the algorithm says an
+/// all-background neighborhood enhances to.
+struct EnhanceRule {
+    algo: Algorithm,
 }
 
-fn apply_algorithm(algo: &Algorithm, image: &Image) -> Image {
-    // determine the size of the image
-    let (width, height) = (image[0].len(), image.len());
-
-    // create the new image to hold the new pixels
-    let mut new_image = vec![vec![false; width]; height];
+impl Rule<bool> for EnhanceRule {
+    fn next_value(&self, neighborhood: [bool; 9], _background: bool) -> bool {
+        let index = neighborhood
+            .iter()
+            .fold(0usize, |acc, &lit| (acc << 1) | lit as usize);
+        self.algo[index]
+    }
 
-    // iterate over the image, applying the algorithm
-    for y in 0..height {
-        for x in 0..width {
-            let value = mask_value(image, x as i32, y as i32);
-            new_image[y][x] = algo[value];
-        }
+    fn next_background(&self, background: bool) -> bool {
+        self.algo[if background { 511 } else { 0 }]
     }
-    new_image
 }
 
-/// Count the number of pixels that are on in the image.
-fn lit_pixels(image: &Image) -> usize {
-    image
-        .iter()
-        .map(|row| row.iter().filter(|&&p| p).count())
-        .sum()
+/// Count the number of lit pixels in the whole (infinite) picture. Returns
+/// `None` when the background itself is lit, since at that point infinitely
+/// many pixels are lit and there is no finite count to report.
+fn count_lit(picture: &InfiniteGrid<bool>) -> Option<usize> {
+    if picture.background {
+        None
+    } else {
+        Some(
+            picture
+                .cells
+                .iter()
+                .map(|row| row.iter().filter(|&&p| p).count())
+                .sum(),
+        )
+    }
 }
 
 /// Print the image to the screen
@@ -136,48 +95,27 @@ fn print_image(image: &Image) {
 fn main() {
     // read the algorithm and image from day20.txt
     let (algo, orig_image) = read_input(include_str!("../../input/day20.txt"));
+    let rule = EnhanceRule { algo };
 
-    // add a margin to the image
-    let image = add_margin(&orig_image, 2);
-
-    // apply the algorithm to the image
-    let image = apply_algorithm(&algo, &image);
-
-    // apply the algorithm one more time
-    let image = apply_algorithm(&algo, &image);
-
-    // shrink to compensate for edge cases:
-    let image = remove_margin(&image, 1);
-
-    // count the number of pixels that are on in the image
-    let lit = lit_pixels(&image);
-
-    // print how many pixels are on
-    println!("Part 1: {} pixels are lit", lit);
-
-    // Part 2
-    // Apply the algorithm 50 times.
-    // --------------------------------------------------
-
-    // first grow the image to allow for a size increase of 50
-    // the problem is, edge cases can grow inward and still influence the picture after 50 iterations
-    // so we need some kind of a safe distance. Maybe like 104 pixels.
-    let mut image = add_margin(&orig_image, 104);
+    // Part 1: apply the algorithm twice.
+    let mut picture = InfiniteGrid::new(orig_image.clone(), false);
+    for _ in 0..2 {
+        picture = picture.step(&rule);
+    }
+    match count_lit(&picture) {
+        Some(lit) => println!("Part 1: {} pixels are lit", lit),
+        None => println!("Part 1: infinitely many pixels are lit"),
+    }
 
-    // apply the algorithm 50 times
+    // Part 2: apply the algorithm 50 times.
+    let mut picture = InfiniteGrid::new(orig_image, false);
     for _ in 0..50 {
-        image = apply_algorithm(&algo, &image);
+        picture = picture.step(&rule);
+    }
+    match count_lit(&picture) {
+        Some(lit) => println!("Part 2: {} pixels are lit", lit),
+        None => println!("Part 2: infinitely many pixels are lit"),
     }
-
-    // now we need to shrink it again so much that we can't have any artifacts of edge cases.
-    // 52 should be enough
-    let image = remove_margin(&image, 52);
-
-    // count the number of pixels that are on in the image
-    let lit = lit_pixels(&image);
-
-    // print how many pixels are on
-    println!("Part 2: {} pixels are lit", lit);
 }
 
 #[cfg(test)]
@@ -208,111 +146,60 @@ mod tests {
         assert!(!image[2][2]);
     }
 
-    #[test]
-    fn test_grow_image() {
-        let margin = 5;
-
-        // make a small image of 1x1
-        let image = vec![vec![true]];
-
-        // add a margin
-        let image = add_margin(&image, margin);
-
-        // assert that the image is now twice the MARGIN + 1
-        assert_eq!(image.len(), margin * 2 + 1);
-
-        // pixel 0,0 should be false
-        assert!(!image[0][0]);
-
-        // pixel MARGIN,MARGIN should be true
-        assert!(image[margin][margin]);
-    }
-
-    #[test]
-    fn test_shrink_image() {
-        let grow = 10;
-        let shrink = 5;
-
-        // make a small image of 1x1
-        let image = vec![vec![true]];
-
-        // add a margin
-        let image = add_margin(&image, grow);
-
-        // assert that the image is now twice the MARGIN + 1
-        assert_eq!(image.len(), grow * 2 + 1);
-
-        // remove the margin
-        let image = remove_margin(&image, shrink);
-
-        // assert the image is now 1 + 2 * (grow - shrink)
-        assert_eq!(image.len(), 1 + 2 * (grow - shrink));
-
-        // middle pixel should be true
-        assert!(image[grow - shrink][grow - shrink]);
-    }
-
-    #[test]
-    fn test_mask_value() {
-        let (algo, image) = read_input(include_str!("../../input/day20_ex.txt"));
-
-        // get the mask value of the middle pixel
-        let mask = mask_value(&image, 2, 2);
-        // according to the example, the mask should be 34
-        assert_eq!(mask, 34);
-
-        // check the algorithm at that position to get the new value of the pixel.
-        let new_value = algo[mask];
-
-        // new value should be true
-        assert!(new_value);
-    }
-
     #[test]
     fn test_grow_and_apply() {
         // read the input
         let (algo, image) = read_input(include_str!("../../input/day20_ex.txt"));
+        let rule = EnhanceRule { algo };
 
-        // add a margin
-        let image = add_margin(&image, 2);
+        let picture = InfiniteGrid::new(image, false);
 
-        // print the new image
-        print_image(&image);
+        // apply the algorithm and get a new picture
+        let picture = picture.step(&rule);
+        print_image(&picture.cells);
 
-        // apply the algorithm and get a new image
-        let new_image = apply_algorithm(&algo, &image);
+        // apply the algorithm to the new picture
+        let picture = picture.step(&rule);
+        print_image(&picture.cells);
 
-        // print the new image
-        print_image(&new_image);
-
-        // apply the algorithm to the new image
-        let new_image = apply_algorithm(&algo, &new_image);
-
-        // print the new image
-        print_image(&new_image);
-
-        // assert that 35 pixels are lit in the new image
-        assert_eq!(lit_pixels(&new_image), 35);
+        // assert that 35 pixels are lit in the new picture, and that the
+        // background has not itself lit up (the example's algo[0] is false)
+        assert_eq!(count_lit(&picture), Some(35));
     }
 
     #[test]
     fn test_algo_fifty_times() {
         // read the input
         let (algo, image) = read_input(include_str!("../../input/day20_ex.txt"));
+        let rule = EnhanceRule { algo };
 
-        // add a margin, accounting for enough space to apply the algorithm 50 times
-        let mut image = add_margin(&image, 50);
+        let mut picture = InfiniteGrid::new(image, false);
 
         // apply the algorithm 50 times
         for _ in 0..50 {
-            let new_image = apply_algorithm(&algo, &image);
-            image = new_image;
+            picture = picture.step(&rule);
         }
 
         // print the new image
-        print_image(&image);
+        print_image(&picture.cells);
 
-        // assert that 35 pixels are lit in the new image
-        assert_eq!(lit_pixels(&image), 3351);
+        // assert that 3351 pixels are lit after 50 steps
+        assert_eq!(count_lit(&picture), Some(3351));
+    }
+
+    #[test]
+    fn test_lit_background_diverges() {
+        // an algorithm whose algo[0] is true means the infinite background
+        // flips to lit on the very first step, so the picture diverges.
+        let mut algo = [false; 512];
+        algo[0] = true;
+        let rule = EnhanceRule { algo };
+        let image = vec![vec![false, false], vec![false, false]];
+        let picture = InfiniteGrid::new(image, false);
+
+        let picture = picture.step(&rule);
+
+        assert!(picture.background);
+        assert_eq!(count_lit(&picture), None);
     }
 }
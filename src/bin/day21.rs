@@ -1,6 +1,7 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 
+use aoc2021::read_strs;
+
 /// A deterministic die with N sides.
 struct Die {
     sides: usize,
@@ -34,7 +35,57 @@ impl Iterator for Die {
     }
 }
 
-const BOARD_SIZE: usize = 10;
+/// Starting positions and rules parsed from the puzzle input. The board
+/// size, die sides and both win thresholds used to be hardcoded, which
+/// meant the worked example (a 10-space board, a 100-sided die, first to
+/// 1000, first to 21) and a user's real input were indistinguishable in
+/// the code; making them fields lets tests build games with other boards
+/// too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GameConfig {
+    p1_start: usize,
+    p2_start: usize,
+    board_size: usize,
+    die_sides: usize,
+    deterministic_win_score: usize,
+    quantum_win_score: usize,
+}
+
+impl GameConfig {
+    /// The puzzle's standard rules: a 10-space board, a 100-sided
+    /// deterministic die, first to 1000 for part one, first to 21 for
+    /// part two. Only the starting positions vary between inputs.
+    fn from_positions(p1_start: usize, p2_start: usize) -> GameConfig {
+        GameConfig {
+            p1_start,
+            p2_start,
+            board_size: 10,
+            die_sides: 100,
+            deterministic_win_score: 1000,
+            quantum_win_score: 21,
+        }
+    }
+}
+
+/// Parse the puzzle format `Player 1 starting position: N` / `Player 2
+/// starting position: M` into a `GameConfig` with the standard board and
+/// win-threshold rules.
+fn parse_config(lines: &[String]) -> Option<GameConfig> {
+    let p1_start = lines
+        .first()?
+        .strip_prefix("Player 1 starting position: ")?
+        .trim()
+        .parse()
+        .ok()?;
+    let p2_start = lines
+        .get(1)?
+        .strip_prefix("Player 2 starting position: ")?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(GameConfig::from_positions(p1_start, p2_start))
+}
 
 #[derive(Debug, Clone, Copy)]
 struct Player {
@@ -47,28 +98,89 @@ impl Player {
         Player { pos, score: 0 }
     }
 
-    fn turn(&mut self, die: &mut Die) {
+    fn turn(&mut self, die: &mut Die, board_size: usize) {
         // roll the die
         let roll = die.roll();
         // move the player
-        self.do_move(roll);
+        self.do_move(roll, board_size);
     }
 
-    fn do_move(&mut self, roll: usize) {
-        self.pos = (self.pos + roll - 1) % BOARD_SIZE + 1;
+    fn do_move(&mut self, roll: usize, board_size: usize) {
+        self.pos = (self.pos + roll - 1) % board_size + 1;
 
         // add the position to the current score
         self.score += self.pos;
     }
 
-    fn has_won(&self) -> bool {
-        self.score >= 1000
+    fn has_won(&self, win_score: usize) -> bool {
+        self.score >= win_score
     }
 }
 
+/// A player's position and score right after a single turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Turn {
+    pos: usize,
+    score: usize,
+}
+
+/// Owns the die and both players for a deterministic-dice game, and
+/// records each player's position/score after every turn so a caller can
+/// inspect how the game unfolded rather than only its final outcome.
+struct Game {
+    die: Die,
+    players: [Player; 2],
+    history: [Vec<Turn>; 2],
+    config: GameConfig,
+}
+
+impl Game {
+    fn new(config: GameConfig) -> Game {
+        Game {
+            die: Die::new(config.die_sides),
+            players: [Player::new(config.p1_start), Player::new(config.p2_start)],
+            history: [Vec::new(), Vec::new()],
+            config,
+        }
+    }
+
+    /// Play turns, alternating players, until one of them reaches the
+    /// deterministic win score. Returns the losing player's final score
+    /// and the total number of rolls it took.
+    fn play_to_completion(&mut self) -> (usize, usize) {
+        loop {
+            for i in 0..self.players.len() {
+                self.players[i].turn(&mut self.die, self.config.board_size);
+                self.history[i].push(Turn {
+                    pos: self.players[i].pos,
+                    score: self.players[i].score,
+                });
+
+                if self.players[i].has_won(self.config.deterministic_win_score) {
+                    let loser = 1 - i;
+                    return (self.players[loser].score, self.die.rolls);
+                }
+            }
+        }
+    }
+}
+
+/// Play a deterministic-dice game from the given starting positions,
+/// using the puzzle's standard rules, and return `(rolls, loser_score)`.
+/// This is the part-1 answer's reusable core: `loser_score * rolls` no
+/// longer has to be computed inline in `main`, and tests can call it with
+/// arbitrary starting positions instead of only the embedded example.
+fn turns_to_win(p1_start: usize, p2_start: usize) -> (usize, usize) {
+    let config = GameConfig::from_positions(p1_start, p2_start);
+    let mut game = Game::new(config);
+    let (loser_score, rolls) = game.play_to_completion();
+    (rolls, loser_score)
+}
+
 /// Each time you roll the dice three times, you split into 27 universes, but
 /// the total of the dice is only between 3 and 9. We combine those cases,
-/// because we nr_u the counter along anyways.
+/// since `recurse` only cares about the resulting total and how many
+/// universes reach it.
 #[rustfmt::skip]
 const QUANTUM_THROWS: [(usize, u128); 7] = [
     (3, 1),
@@ -86,79 +198,63 @@ type State = (usize, usize, usize, usize);
 //                   p1wu, p2wu
 type UniverseWins = (u128, u128);
 
-thread_local! {
-    static CACHE: RefCell<HashMap<(State, u128), UniverseWins>> = RefCell::new(HashMap::new());
-}
-
-fn recurse_game(init_state: State, nr_u: u128) -> UniverseWins {
-    // check if we have already calculated this state
-    if let Some(res) = CACHE.with(|cache| cache.borrow().get(&(init_state, nr_u)).cloned()) {
-        return res;
+/// Win counts for a single universe entering `state` with the current
+/// player to move, as `(current_player_wins, other_player_wins)`.
+///
+/// The old version keyed its cache on `(state, nr_u)` and returned counts
+/// already multiplied by `nr_u`, but `nr_u` takes a different value on
+/// almost every path, so the cache barely ever hit. Computing the
+/// single-universe result and letting the caller apply the multiplier
+/// means the cache only has to key on `state`, so equivalent positions
+/// reached through different rolls actually reuse each other's work.
+fn recurse(
+    state: State,
+    board_size: usize,
+    win_score: usize,
+    cache: &mut HashMap<State, UniverseWins>,
+) -> UniverseWins {
+    if let Some(res) = cache.get(&state) {
+        return *res;
     }
 
-    // Do a turn for p1.
-    let win_universes = QUANTUM_THROWS
+    let wins = QUANTUM_THROWS
         .iter()
-        .map(|(value, universes)| {
-            let mut state = (init_state.0, init_state.1, init_state.2, init_state.3);
-
-            // move player 1
-            state.0 = (state.0 + value - 1) % BOARD_SIZE + 1;
-            // add the position to the current score
-            state.2 += state.0;
+        .map(|(roll, count)| {
+            // move the current player and add their new position to their score
+            let pos = (state.0 + roll - 1) % board_size + 1;
+            let score = state.2 + pos;
 
-            // if p1 has won, return the universes.
-            if state.2 >= 21 {
-                (*universes * nr_u, 0)
+            if score >= win_score {
+                (*count, 0)
             } else {
-                // give player 2 the turn, so flip the player data around.
-                let state = (state.1, state.0, state.3, state.2);
-
-                let (p2wu, p1wu) = recurse_game(state, *universes);
-                (p1wu * nr_u, p2wu * nr_u)
+                // swap the players, so the next recursion moves the other one
+                let next_state = (state.1, pos, state.3, score);
+                let (next_wins, cur_wins) = recurse(next_state, board_size, win_score, cache);
+                (count * cur_wins, count * next_wins)
             }
         })
         .reduce(|(p1wu, p2wu), (p1wu2, p2wu2)| (p1wu + p1wu2, p2wu + p2wu2))
         .expect("No win chancees!");
 
-    // put this in the cache
-    CACHE.with(|cache| {
-        cache.borrow_mut().insert((init_state, nr_u), win_universes);
-    });
+    cache.insert(state, wins);
+    wins
+}
 
-    (win_universes.0, win_universes.1)
+fn recurse_game(init_state: State, board_size: usize, win_score: usize) -> UniverseWins {
+    let mut cache = HashMap::new();
+    recurse(init_state, board_size, win_score, &mut cache)
 }
 
 fn main() {
-    // create a new die
-    let mut die = Die::new(100);
-
-    // create player 1 and player 2
-    let mut p1 = Player::new(7);
-    let mut p2 = Player::new(3);
+    let lines = read_strs("input/day21.txt");
+    let config = parse_config(&lines).expect("failed to parse starting positions");
 
-    loop {
-        p1.turn(&mut die);
-
-        if p1.has_won() {
-            println!("Player 1 wins!");
-            break;
-        }
-
-        p2.turn(&mut die);
-
-        if p2.has_won() {
-            println!("Player 2 wins!");
-            break;
-        }
-    }
-
-    let loser_score = if p1.has_won() { p2.score } else { p1.score };
+    let (rolls, loser_score) = turns_to_win(config.p1_start, config.p2_start);
 
     println!("The loser scored {}", loser_score);
 
     // multiply loser score by die rolls and print out that number
-    println!("Part 1: Game outcome: {}", loser_score * die.rolls);
+    println!("Part 1: Game outcome: {}", loser_score * rolls);
 
     // Part 2
     // ------
@@ -173,13 +269,9 @@ fn main() {
     // The game ends when the player's score is 21 or higher.
     //
     // Maybe we should just try recursion and see how far we get.
-    //
-    // make two new players:
-    // on position 7 and 3
-    // with score 0
-    let state = (7, 3, 0, 0);
+    let state = (config.p1_start, config.p2_start, 0, 0);
 
-    let (p1wu, p2wu) = recurse_game(state, 1);
+    let (p1wu, p2wu) = recurse_game(state, config.board_size, config.quantum_win_score);
 
     // printout p1wu and p2wu
     println!(
@@ -221,44 +313,44 @@ mod tests {
         let mut p1 = Player::new(4);
         let mut p2 = Player::new(8);
 
-        p1.turn(&mut die);
+        p1.turn(&mut die, 10);
 
         // player should move to position 10 and have a score of 10.
         assert_eq!(p1.pos, 10);
         assert_eq!(p1.score, 10);
 
         // Player 2 rolls 4+5+6 and moves to space 3 for a total score of 3.
-        p2.turn(&mut die);
+        p2.turn(&mut die, 10);
         assert_eq!(p2.pos, 3);
         assert_eq!(p2.score, 3);
 
         // Player 1 rolls 7+8+9 and moves to space 4 for a total score of 14.
-        p1.turn(&mut die);
+        p1.turn(&mut die, 10);
         assert_eq!(p1.pos, 4);
         assert_eq!(p1.score, 14);
 
         // Player 2 rolls 10+11+12 and moves to space 6 for a total score of 9.
-        p2.turn(&mut die);
+        p2.turn(&mut die, 10);
         assert_eq!(p2.pos, 6);
         assert_eq!(p2.score, 9);
 
         // Player 1 rolls 13+14+15 and moves to space 6 for a total score of 20.
-        p1.turn(&mut die);
+        p1.turn(&mut die, 10);
         assert_eq!(p1.pos, 6);
         assert_eq!(p1.score, 20);
 
         // Player 2 rolls 16+17+18 and moves to space 7 for a total score of 16.
-        p2.turn(&mut die);
+        p2.turn(&mut die, 10);
         assert_eq!(p2.pos, 7);
         assert_eq!(p2.score, 16);
 
         // Player 1 rolls 19+20+21 and moves to space 6 for a total score of 26.
-        p1.turn(&mut die);
+        p1.turn(&mut die, 10);
         assert_eq!(p1.pos, 6);
         assert_eq!(p1.score, 26);
 
         // Player 2 rolls 22+23+24 and moves to space 6 for a total score of 22.
-        p2.turn(&mut die);
+        p2.turn(&mut die, 10);
         assert_eq!(p2.pos, 6);
         assert_eq!(p2.score, 22);
     }
@@ -271,18 +363,18 @@ mod tests {
         let mut p2 = Player::new(8);
 
         loop {
-            p1.turn(&mut die);
-            if p1.has_won() {
+            p1.turn(&mut die, 10);
+            if p1.has_won(1000) {
                 break;
             }
-            p2.turn(&mut die);
-            if p2.has_won() {
+            p2.turn(&mut die, 10);
+            if p2.has_won(1000) {
                 break;
             }
         }
 
         // player one should have won
-        assert!(p1.has_won());
+        assert!(p1.has_won(1000));
 
         // player one should have a score of over 1000
         assert!(p1.score >= 1000);
@@ -294,6 +386,32 @@ mod tests {
         assert_eq!(die.rolls, 993);
     }
 
+    #[test]
+    fn test_game_play_to_completion() {
+        let config = GameConfig::from_positions(4, 8);
+        let mut game = Game::new(config);
+
+        let (loser_score, rolls) = game.play_to_completion();
+
+        // same outcome as test_game_end, but driven through Game instead
+        // of manually alternating turns.
+        assert_eq!(loser_score, 745);
+        assert_eq!(rolls, 993);
+
+        // player 1's first turn should be recorded: moves to space 10
+        // for a score of 10, same as in test_play_game.
+        assert_eq!(game.history[0][0], Turn { pos: 10, score: 10 });
+    }
+
+    #[test]
+    fn test_turns_to_win() {
+        let (rolls, loser_score) = turns_to_win(4, 8);
+
+        assert_eq!(rolls, 993);
+        assert_eq!(loser_score, 745);
+        assert_eq!(loser_score * rolls, 739785);
+    }
+
     #[test]
     fn test_recurse_game() {
         // Create two players where on is on the virge of winning.
@@ -301,7 +419,7 @@ mod tests {
 
         // in this situation, player 1 should always win, because it's his turn, and he will
         // score more than 21
-        let (p1wu, p2wu) = recurse_game(state, 1);
+        let (p1wu, p2wu) = recurse_game(state, 10, 21);
 
         // player 1 throws the die 3 times, which splits the universe into 3 * 3 * 3 = 27 universes.
         // so p1wu should be 27
@@ -316,7 +434,7 @@ mod tests {
         let state = (1, 2, 0, 20);
 
         // in this situation, player 2 should always win, because player 1 can not win in one move.
-        let (p1wu, p2wu) = recurse_game(state, 1);
+        let (p1wu, p2wu) = recurse_game(state, 10, 21);
 
         // player 1 throws the die 3 times, which splits the universe into 3 * 3 * 3 = 27 universes.
         // player 2 then does the same and wins, in all 27 * 27 universes.
@@ -332,7 +450,7 @@ mod tests {
         // happens in 1 universe. In all other universes, player 2 will win.
 
         let state = (7, 2, 11, 20);
-        let (p1wu, p2wu) = recurse_game(state, 1);
+        let (p1wu, p2wu) = recurse_game(state, 10, 21);
 
         // player 1 should only win in 1 universe
         assert_eq!(p1wu, 1);
@@ -344,11 +462,47 @@ mod tests {
     fn test_recurse_game_example() {
         let state = (4, 8, 0, 0);
 
-        let (p1wu, p2wu) = recurse_game(state, 1);
+        let (p1wu, p2wu) = recurse_game(state, 10, 21);
 
         // Using the same starting positions as in the example above, player 1 wins in 444356092776315 universes,
         assert_eq!(p1wu, 444356092776315);
         // while player 2 merely wins in 341960390180808 universes.
         assert_eq!(p2wu, 341960390180808);
     }
+
+    #[test]
+    fn test_recurse_game_non_default_board() {
+        // Each recurse_game call builds its own cache, keyed on state only,
+        // so a tiny 4-space board reaching score 1 shouldn't collide with
+        // the standard 10-space/21-point games exercised by the other tests.
+        let state = (1, 1, 0, 0);
+
+        let (p1wu, p2wu) = recurse_game(state, 4, 1);
+
+        // player 1 wins outright on the very first roll, in all 27 universes.
+        assert_eq!(p1wu, 27);
+        assert_eq!(p2wu, 0);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let lines = vec![
+            "Player 1 starting position: 4".to_string(),
+            "Player 2 starting position: 8".to_string(),
+        ];
+
+        let config = parse_config(&lines).expect("should parse");
+        assert_eq!(config.p1_start, 4);
+        assert_eq!(config.p2_start, 8);
+        assert_eq!(config.board_size, 10);
+        assert_eq!(config.die_sides, 100);
+        assert_eq!(config.deterministic_win_score, 1000);
+        assert_eq!(config.quantum_win_score, 21);
+    }
+
+    #[test]
+    fn test_parse_config_malformed() {
+        let lines = vec!["not a starting position".to_string()];
+        assert_eq!(parse_config(&lines), None);
+    }
 }
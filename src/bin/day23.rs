@@ -1,5 +1,5 @@
 use cached::proc_macro::cached;
-use pathfinding::prelude::dijkstra;
+use pathfinding::prelude::{astar, dijkstra};
 use std::collections::HashMap;
 
 /// We need an efficient way to represent the map map in order to use efficient ways of checking
@@ -24,6 +24,70 @@ type Pod = (usize, char);
 /// a route is a series of indexes not including the start index
 type Route = Vec<usize>;
 
+fn cell_to_code(c: char) -> u128 {
+    match c {
+        '.' => 0,
+        'A' => 1,
+        'B' => 2,
+        'C' => 3,
+        'D' => 4,
+        _ => unreachable!("not a valid map cell: {c}"),
+    }
+}
+
+fn code_to_cell(code: u128) -> char {
+    match code {
+        0 => '.',
+        1 => 'A',
+        2 => 'B',
+        3 => 'C',
+        4 => 'D',
+        _ => unreachable!("not a valid packed cell code: {code}"),
+    }
+}
+
+/// A packed encoding of a `Map`: each of the up to 23 cells takes 3 bits
+/// (`0` = empty, `1..=4` = `A..D`), packed into a single `u128`. Occupancy
+/// checks become O(1) bit shifts instead of `O(n)` `chars().nth(i)` scans,
+/// and comparing/hashing a `PackedMap` during search is a single integer
+/// operation instead of hashing a whole string. The `String` form stays the
+/// boundary type for parsing, printing, and the public solver entry points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PackedMap {
+    bits: u128,
+    len: usize,
+}
+
+impl PackedMap {
+    fn from_map(map: &Map) -> PackedMap {
+        let mut bits = 0u128;
+        for (i, c) in map.chars().enumerate() {
+            bits |= cell_to_code(c) << (i * 3);
+        }
+        PackedMap {
+            bits,
+            len: map.len(),
+        }
+    }
+
+    fn to_map(self) -> Map {
+        (0..self.len).map(|i| code_to_cell(self.cell(i))).collect()
+    }
+
+    fn cell(&self, i: usize) -> u128 {
+        (self.bits >> (i * 3)) & 0b111
+    }
+
+    fn set_cell(&mut self, i: usize, code: u128) {
+        let mask = 0b111_u128 << (i * 3);
+        self.bits = (self.bits & !mask) | (code << (i * 3));
+    }
+
+    fn is_empty_cell(&self, i: usize) -> bool {
+        self.cell(i) == 0
+    }
+}
+
 /// Return an iterator over the pods in the map
 fn pods_iter(map: &Map) -> impl Iterator<Item = Pod> + '_ {
     map.chars()
@@ -69,21 +133,25 @@ fn energy(pod: &Pod) -> u32 {
 
 /// Return the contents of the burrow. For simplicity sake, we can pass in a Char, since
 /// that's likely what we have from the context that we make this call.
-fn burrow(c: &char, map: &Map) -> Vec<char> {
+fn burrow(c: char, map: &PackedMap) -> Vec<char> {
     // only return the chars that are in the burrow
-    match c {
-        'A' => map.chars().skip(7).step_by(4).collect(),
-        'B' => map.chars().skip(8).step_by(4).collect(),
-        'C' => map.chars().skip(9).step_by(4).collect(),
-        'D' => map.chars().skip(10).step_by(4).collect(),
-        _ => vec![],
-    }
+    let col = match c {
+        'A' => 0,
+        'B' => 1,
+        'C' => 2,
+        'D' => 3,
+        _ => return vec![],
+    };
+    let depth = (map.len - 7) / 4;
+    (0..depth)
+        .map(|row| code_to_cell(map.cell(7 + row * 4 + col)))
+        .collect()
 }
 
 /// Check if the pod may move, by looking at some easy facts like it being in a hallway
 /// or in a burrow. If it's in a burrow, it may only move if the burrow contains other
 /// characters than the pod.
-fn may_move(pod: &Pod, map: &Map) -> bool {
+fn may_move(pod: &Pod, map: &PackedMap) -> bool {
     if !in_burrow(pod) {
         return true;
     }
@@ -95,7 +163,7 @@ fn may_move(pod: &Pod, map: &Map) -> bool {
     // if the pod is in it's destination burrow, and the burrow contains no other pods, it's not
     // allowed to move
     let (_, c) = pod;
-    let burrow = burrow(c, map);
+    let burrow = burrow(*c, map);
 
     // burrow should contain other characters than c for the pod to be allowed to move
     burrow.iter().filter(|x| *x != c && *x != &'.').count() > 0
@@ -179,8 +247,11 @@ fn route_steps(route: &Route) -> u32 {
 }
 
 /// Return a vector of possible routes a pod can take, without considering if those positions are
-/// occupied or not, that will be handled by a different function.
-fn routes_from(pod: &Pod, deep: bool) -> Vec<Route> {
+/// occupied or not, that will be handled by a different function. `depth` is the number of rows
+/// in each burrow column (2 for the standard puzzle, 4 for the "unfolded" one, or anything else
+/// for a custom-sized burrow), so a pod in the hallway gets one candidate route per slot in its
+/// home column, at whatever depth the map actually has.
+fn routes_from(pod: &Pod, depth: usize) -> Vec<Route> {
     let mut routes = vec![];
     if in_burrow(pod) {
         // we can move to the hallway
@@ -188,46 +259,16 @@ fn routes_from(pod: &Pod, deep: bool) -> Vec<Route> {
             routes.push(trace(pod.0, i));
         }
     } else {
-        // in the hallway, can only move back into home burrow
-        match pod.1 {
-            'A' => {
-                routes.push(trace(pod.0, 7));
-                routes.push(trace(pod.0, 11));
-            }
-            'B' => {
-                routes.push(trace(pod.0, 8));
-                routes.push(trace(pod.0, 12));
-            }
-            'C' => {
-                routes.push(trace(pod.0, 9));
-                routes.push(trace(pod.0, 13));
-            }
-            'D' => {
-                routes.push(trace(pod.0, 10));
-                routes.push(trace(pod.0, 14));
-            }
-            _ => {}
-        }
-        if deep {
-            match pod.1 {
-                'A' => {
-                    routes.push(trace(pod.0, 15));
-                    routes.push(trace(pod.0, 19));
-                }
-                'B' => {
-                    routes.push(trace(pod.0, 16));
-                    routes.push(trace(pod.0, 20));
-                }
-                'C' => {
-                    routes.push(trace(pod.0, 17));
-                    routes.push(trace(pod.0, 21));
-                }
-                'D' => {
-                    routes.push(trace(pod.0, 18));
-                    routes.push(trace(pod.0, 22));
-                }
-                _ => {}
-            }
+        // in the hallway, can only move back into home burrow, into any row of it
+        let col = match pod.1 {
+            'A' => 0,
+            'B' => 1,
+            'C' => 2,
+            'D' => 3,
+            _ => return routes,
+        };
+        for row in 0..depth {
+            routes.push(trace(pod.0, 7 + row * 4 + col));
         }
     }
     // remove the routes that are empty
@@ -235,8 +276,8 @@ fn routes_from(pod: &Pod, deep: bool) -> Vec<Route> {
 }
 
 /// Check the map to see if a route is clear of any other pods.
-pub fn route_clear(route: &Route, map: &Map) -> bool {
-    route.iter().all(|i| map.chars().nth(*i).unwrap() == '.')
+fn route_clear(route: &Route, map: &PackedMap) -> bool {
+    route.iter().all(|&i| map.is_empty_cell(i))
 }
 
 /// Convenience function to print the map in the same format as is used in the puzzle.
@@ -277,13 +318,13 @@ pub fn print_map(map: &Map) {
 
 /// Now we need a function that returns the lowest empty burrows, so we can filter out the routes
 /// that have a destination burrow position that is not the lowest.
-#[cached(key = "String", convert = r#"{ String::from(map) }"#)]
-fn get_lowest_empty_burrows(map: &Map) -> Vec<usize> {
+#[cached(key = "u128", convert = r#"{ map.bits }"#)]
+fn get_lowest_empty_burrows(map: PackedMap) -> Vec<usize> {
     let mut lowest = [0_usize; 4];
     let mut i = 7;
-    while i < map.len() {
+    while i < map.len {
         for j in 0..4 {
-            if map.chars().nth(i + j).unwrap() == '.' {
+            if map.is_empty_cell(i + j) {
                 lowest[j] = i + j;
             }
         }
@@ -295,14 +336,15 @@ fn get_lowest_empty_burrows(map: &Map) -> Vec<usize> {
 
 /// Check if the burrow is dirty, meaning that it contains other pods than the pod that is moving
 /// into it.
-fn burrow_dirty(pod: &Pod, map: &Map) -> bool {
-    let burrow = burrow(&pod.1, map);
+fn burrow_dirty(pod: &Pod, map: &PackedMap) -> bool {
+    let burrow = burrow(pod.1, map);
     burrow.iter().filter(|c| *c != &pod.1 && **c != '.').count() > 0
 }
 
 /// Now we only need a function that returns a vec of possible moves, along with the cost.
 pub fn moves(map: &Map) -> Vec<(Map, u32)> {
-    let deep = map.len() > 15;
+    let packed = PackedMap::from_map(map);
+    let depth = (map.len() - 7) / 4;
     let mut moves = vec![];
     // determining moves is quite easy
     // for each index where a pod can be
@@ -311,16 +353,16 @@ pub fn moves(map: &Map) -> Vec<(Map, u32)> {
     // each route index should be empty, the last route index is the new position and the count
     // of moves is the cost.
     pods_iter(map)
-        .filter(|pod| may_move(pod, map))
+        .filter(|pod| may_move(pod, &packed))
         .for_each(|pod| {
             // if the pod is in the hallway, and the burrow is 'dirty', skip
-            if in_hallway(&pod) && burrow_dirty(&pod, &map) {
+            if in_hallway(&pod) && burrow_dirty(&pod, &packed) {
                 return;
             }
-            let routes = routes_from(&pod, deep);
+            let routes = routes_from(&pod, depth);
 
             // get lowest empty burrow indexes
-            let lowest_burrows = get_lowest_empty_burrows(&map);
+            let lowest_burrows = get_lowest_empty_burrows(packed);
             // remove any routes that have a burrow as a destination (i >= 7) which is not
             // in the lowest burrows
             let routes = routes
@@ -330,7 +372,7 @@ pub fn moves(map: &Map) -> Vec<(Map, u32)> {
                 .collect::<Vec<Route>>();
 
             for route in routes {
-                if route_clear(&route, map) {
+                if route_clear(&route, &packed) {
                     let mut new_map = map.clone();
                     let (from, c) = pod;
                     let to = *route.last().unwrap();
@@ -368,6 +410,28 @@ pub fn parse(input: &str) -> Map {
     map
 }
 
+/// Parse a full puzzle diagram, including the hallway row, so that arbitrary
+/// mid-game states can be solved, not just the standard empty-hallway start
+/// position that `parse` assumes. The 11-wide hallway row has pods (or `.`)
+/// at every position, but only the 7 reachable cells (the entrance columns
+/// are skipped) are kept, mirroring the layout `Map` already uses.
+pub fn parse_state(input: &str) -> Map {
+    let lines: Vec<&str> = input.lines().collect();
+
+    // the hallway row is the second line, e.g. "#...........#"
+    let hallway: Vec<char> = lines[1].chars().skip(1).take(11).collect();
+    const REACHABLE: [usize; 7] = [0, 1, 3, 5, 7, 9, 10];
+    let mut map: Map = REACHABLE.iter().map(|&i| hallway[i]).collect();
+
+    // every remaining line except the bottom wall is a burrow row; pull out
+    // its 4 pod characters (a pod letter or `.` for an empty slot).
+    for line in &lines[2..lines.len() - 1] {
+        map.extend(line.chars().filter(|c| c.is_alphabetic() || *c == '.'));
+    }
+
+    map
+}
+
 /// Solve the puzzle by finding the shortest path to the win map.
 /// This is a naive approach using a queue. It can be used to solve the first part, but not really
 /// for the second part.
@@ -418,6 +482,99 @@ fn solve_dijkstra(map: &Map, win_map: &Map) -> u32 {
     }
 }
 
+/// Solve the shortest path to the win map and return the full solution as an
+/// ordered sequence of `(map, step_energy)` pairs, plus the grand total, so
+/// callers can render, animate, or verify a solution programmatically
+/// instead of parsing the maps `solve_dijkstra` prints to stdout. Reuses
+/// `pathfinding::dijkstra`'s returned node sequence, recovering each step's
+/// cost by looking it back up via `moves`.
+pub fn solve_path(map: &Map, win_map: &Map) -> Option<(Vec<(Map, u32)>, u32)> {
+    let (path, total) = dijkstra(map, moves, |m| m == win_map)?;
+
+    let steps = path
+        .windows(2)
+        .map(|pair| {
+            let (from, to) = (&pair[0], &pair[1]);
+            let cost = moves(from)
+                .into_iter()
+                .find(|(m, _)| m == to)
+                .map(|(_, cost)| cost)
+                .expect("consecutive path maps must be connected by a move");
+            (to.clone(), cost)
+        })
+        .collect();
+
+    Some((steps, total))
+}
+
+/// Maps a pod's letter to its home burrow column index (0 = A, 3 = D).
+fn home_col_index(c: char) -> usize {
+    match c {
+        'A' => 0,
+        'B' => 1,
+        'C' => 2,
+        'D' => 3,
+        _ => unreachable!("not a pod: {c}"),
+    }
+}
+
+/// Real x-coordinates (from the original, uncompressed 11-cell hallway) of the
+/// 7 positions a pod may actually stop on. Burrow entrances sit at x=2,4,6,8
+/// and are never valid stopping positions, so they're excluded here.
+const HALLWAY_X: [i32; 7] = [0, 1, 3, 5, 7, 9, 10];
+
+/// The x-coordinate of the hallway entrance above burrow column `col`.
+fn burrow_entrance_x(col: usize) -> i32 {
+    2 + 2 * col as i32
+}
+
+/// An admissible heuristic for A*: for every pod not yet settled in its final
+/// resting place, add the minimum energy it must spend to get home, ignoring
+/// every other pod entirely. A pod in the hallway only needs to cross to its
+/// home column's entrance and step down; a pod in a burrow (wrong column, or
+/// blocking a foreign pod underneath it in its own column) must also first
+/// rise out of its current burrow. Since this relaxation drops every
+/// collision constraint, it can only ever be cheaper than the real route, so
+/// the sum never overestimates the true remaining cost.
+fn heuristic(map: &Map) -> u32 {
+    let packed = PackedMap::from_map(map);
+    pods_iter(map)
+        .filter(|pod| !in_home_burrow(pod) || burrow_dirty(pod, &packed))
+        .map(|(idx, c)| {
+            let home_col = home_col_index(c);
+            let e = energy(&(idx, c));
+            if in_hallway(&(idx, c)) {
+                let horizontal = (HALLWAY_X[idx] - burrow_entrance_x(home_col)).abs() as u32;
+                e * (horizontal + 1)
+            } else {
+                let row = (idx - 7) / 4;
+                let col = (idx - 7) % 4;
+                let rise = row as u32 + 1;
+                let horizontal =
+                    (burrow_entrance_x(col) - burrow_entrance_x(home_col)).abs() as u32;
+                e * (rise + horizontal + 1)
+            }
+        })
+        .sum()
+}
+
+/// Solve the shortest path to the win map using the `pathfinding` crate's A*
+/// implementation, guided by `heuristic`. Correctness is identical to
+/// `solve_dijkstra`, but the admissible lower bound lets A* prune states
+/// Dijkstra would otherwise have to expand, which matters a lot on the
+/// 23-cell XL map.
+pub fn solve_astar(map: &Map, win_map: &Map) -> u32 {
+    let result = astar(map, moves, heuristic, |map| map == win_map);
+    if let Some((path, energy)) = result {
+        for map in path.iter() {
+            print_map(map);
+        }
+        energy
+    } else {
+        0
+    }
+}
+
 pub fn main() {
     let _input_a = r#"#############
 #...........#
@@ -441,7 +598,7 @@ pub fn main() {
 }
 
 /// Here starts the testing
-/// 
+///
 /// The tests are a bit verbose, but they are necessary to make sure the functions are working
 /// correctly, and most test cases are there to help detect and fix bugs.
 ///
@@ -462,6 +619,32 @@ mod tests {
         assert_eq!(map, ".......ABCDABCD");
     }
 
+    #[test]
+    fn test_parse_state_start_position() {
+        let input = r#"#############
+#...........#
+###A#B#C#D###
+  #A#B#C#D#
+  #########
+"#;
+        let map = parse_state(input);
+        assert_eq!(map, ".......ABCDABCD");
+    }
+
+    #[test]
+    fn test_parse_state_mid_game() {
+        // a hallway with two pods parked in it (on valid stopping squares),
+        // and partially emptied burrows
+        let input = r#"#############
+#.B.......C.#
+###.#.#A#D###
+  #A#B#.#D#
+  #########
+"#;
+        let map = parse_state(input);
+        assert_eq!(map, ".B...C...ADAB.D");
+    }
+
     #[test]
     /// Test the example to see if it finds the moves that are suggested.
     fn test_moves_1() {
@@ -560,7 +743,7 @@ mod tests {
         print_map(&from);
         println!("to:");
         print_map(&to);
-        println!("moves:"); 
+        println!("moves:");
         for (map, _) in &m {
             print_map(map);
         }
@@ -570,6 +753,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_solve_path() {
+        let map = ".....A..BCDABCD".to_string();
+        let (steps, total) = solve_path(&map, &String::from(WIN_MAP)).expect("should find a path");
+
+        assert_eq!(total, 8);
+        // the path should end on the win map, and the step costs should sum to the total
+        assert_eq!(steps.last().unwrap().0, WIN_MAP);
+        assert_eq!(steps.iter().map(|(_, cost)| cost).sum::<u32>(), total);
+    }
+
     #[rstest]
     #[case(".....A..BCDABCD", 8)]
     #[case("...DDA..BC.ABC.", 7000 + 8)]
@@ -602,39 +796,78 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(".....A..BCDABCD", 8)]
+    #[case("...DDA..BC.ABC.", 7000 + 8)]
+    #[case("...D....BCDABCA", 2003 + 7000 + 8)]
+    fn test_solve_astar_example(#[case] map: &str, #[case] energy: u32) {
+        assert_eq!(
+            solve_astar(&map.to_string(), &String::from(WIN_MAP)),
+            energy,
+            "map: {map} should cost energy {energy}"
+        );
+    }
+
     #[test]
     fn test_burrow_function() {
-        let map = ".......BCBDADCA".to_string();
-        assert_eq!(burrow(&'A', &map), vec!['B', 'A']);
-        assert_eq!(burrow(&'B', &map), vec!['C', 'D']);
-        assert_eq!(burrow(&'C', &map), vec!['B', 'C']);
-        assert_eq!(burrow(&'D', &map), vec!['D', 'A']);
+        let map = PackedMap::from_map(&".......BCBDADCA".to_string());
+        assert_eq!(burrow('A', &map), vec!['B', 'A']);
+        assert_eq!(burrow('B', &map), vec!['C', 'D']);
+        assert_eq!(burrow('C', &map), vec!['B', 'C']);
+        assert_eq!(burrow('D', &map), vec!['D', 'A']);
     }
 
     #[test]
     fn test_may_move() {
-        let map = ".......BCBDADCD".to_string();
+        let map = PackedMap::from_map(&".......BCBDADCD".to_string());
         assert_eq!(may_move(&(7, 'B'), &map), true);
         assert_eq!(may_move(&(8, 'C'), &map), true);
         assert_eq!(may_move(&(9, 'B'), &map), true);
         assert_eq!(may_move(&(10, 'D'), &map), false);
 
-        let map = "..B....BC.DADCA".to_string();
+        let map = PackedMap::from_map(&"..B....BC.DADCA".to_string());
         assert_eq!(may_move(&(8, 'C'), &map), true);
         assert_eq!(may_move(&(13, 'C'), &map), false);
 
-        let map = "AA.CBBDB...DC..DB.CADCA".to_string();
+        let map = PackedMap::from_map(&"AA.CBBDB...DC..DB.CADCA".to_string());
         assert_eq!(may_move(&(3, 'C'), &map), true);
     }
 
+    #[test]
+    fn test_packed_map_to_map_round_trip() {
+        let original = ".......BCBDADCA".to_string();
+        let map = PackedMap::from_map(&original);
+        assert_eq!(map.to_map(), original);
+    }
+
+    #[test]
+    fn test_packed_map_set_cell() {
+        let mut map = PackedMap::from_map(&".......BCBDADCA".to_string());
+        assert!(map.is_empty_cell(0));
+
+        map.set_cell(0, cell_to_code('A'));
+
+        assert!(!map.is_empty_cell(0));
+        assert_eq!(map.to_map(), "A......BCBDADCA");
+    }
+
     #[test]
     fn test_routes_from() {
         let pod = (0, 'A');
-        let routes = routes_from(&pod, false);
+        let routes = routes_from(&pod, 2);
         assert_eq!(routes.len(), 2);
         assert_eq!(routes, vec![vec![1, 7], vec![1, 7, 11]]);
     }
 
+    #[test]
+    fn test_routes_from_custom_depth() {
+        // a depth of 3 should offer one route per row of the home column
+        let pod = (0, 'A');
+        let routes = routes_from(&pod, 3);
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes, vec![vec![1, 7], vec![1, 7, 11], vec![1, 7, 11, 15]]);
+    }
+
     #[test]
     fn test_trace() {
         assert_eq!(trace(0, 7), vec![1, 7]);
@@ -54,6 +54,306 @@ impl From<&str> for Instruction {
     }
 }
 
+/// Build a `Vec<Instruction>` from the mnemonic form directly, e.g.
+/// `alu! { inp w; mul x 0; add x z; mod x 26; div z 1; eql x w }`, instead
+/// of spelling out `Instruction::Mul('x', Param::Num(0))` by hand. Operands
+/// are bare identifiers for registers (`x`, `y`, ...) or integer literals
+/// (negative ones included) for numeric params, routed through the same
+/// `Instruction`/`Param` construction `From<&str>` uses.
+#[allow(unused_macros)]
+macro_rules! alu {
+    (@acc [ $( $out:expr ),* ]) => {
+        vec![ $( $out ),* ]
+    };
+
+    // Once an instruction's own tokens are fully matched, either there's a
+    // `;` and more lines follow, or this was the last line.
+    (@push [ $( $out:expr ),* ] $instr:expr ; $( $rest:tt )*) => {
+        alu!(@acc [ $( $out, )* $instr ] $( $rest )*)
+    };
+    (@push [ $( $out:expr ),* ] $instr:expr) => {
+        alu!(@acc [ $( $out, )* $instr ])
+    };
+
+    (@acc [ $( $out:expr ),* ] inp $a:ident $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Inp(stringify!($a).chars().next().unwrap())
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] add $a:ident $b:ident $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Add(stringify!($a).chars().next().unwrap(), Param::Var(stringify!($b).chars().next().unwrap()))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] add $a:ident - $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Add(stringify!($a).chars().next().unwrap(), Param::Num(-$b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] add $a:ident $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Add(stringify!($a).chars().next().unwrap(), Param::Num($b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] mul $a:ident $b:ident $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Mul(stringify!($a).chars().next().unwrap(), Param::Var(stringify!($b).chars().next().unwrap()))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] mul $a:ident - $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Mul(stringify!($a).chars().next().unwrap(), Param::Num(-$b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] mul $a:ident $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Mul(stringify!($a).chars().next().unwrap(), Param::Num($b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] div $a:ident $b:ident $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Div(stringify!($a).chars().next().unwrap(), Param::Var(stringify!($b).chars().next().unwrap()))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] div $a:ident - $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Div(stringify!($a).chars().next().unwrap(), Param::Num(-$b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] div $a:ident $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Div(stringify!($a).chars().next().unwrap(), Param::Num($b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] mod $a:ident $b:ident $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Mod(stringify!($a).chars().next().unwrap(), Param::Var(stringify!($b).chars().next().unwrap()))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] mod $a:ident - $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Mod(stringify!($a).chars().next().unwrap(), Param::Num(-$b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] mod $a:ident $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Mod(stringify!($a).chars().next().unwrap(), Param::Num($b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] eql $a:ident $b:ident $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Eql(stringify!($a).chars().next().unwrap(), Param::Var(stringify!($b).chars().next().unwrap()))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] eql $a:ident - $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Eql(stringify!($a).chars().next().unwrap(), Param::Num(-$b))
+            $( $rest )*)
+    };
+    (@acc [ $( $out:expr ),* ] eql $a:ident $b:literal $( $rest:tt )*) => {
+        alu!(@push [ $( $out ),* ]
+            Instruction::Eql(stringify!($a).chars().next().unwrap(), Param::Num($b))
+            $( $rest )*)
+    };
+
+    ( $( $tok:tt )* ) => {
+        alu!(@acc [] $( $tok )*)
+    };
+}
+
+/// A symbolic value tracked while walking the program once, instead of
+/// running it on 9^14-many concrete inputs. Each `Inp` allocates a fresh
+/// `Input(k)`; everything else mirrors the corresponding `Instruction`
+/// variant. Building these through `make_add`/`make_mul`/... rather than
+/// the bare constructors means the tree is simplified as it's built, so by
+/// the time a whole MONAD program has been folded, most of its repeated
+/// per-digit blocks collapse down to something small enough to read.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Input(usize),
+    Lit(Int),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Mod(Box<Expr>, Box<Expr>),
+    Eql(Box<Expr>, Box<Expr>),
+}
+
+#[allow(dead_code)]
+impl Expr {
+    /// The `(min, max)` bounds `self` could possibly evaluate to, used by
+    /// the `make_*` simplifications to decide things like whether a `mod`
+    /// is a no-op or an `eql` can never be true.
+    fn range(&self) -> (Int, Int) {
+        match self {
+            Expr::Input(_) => (1, 9),
+            Expr::Lit(v) => (*v, *v),
+            Expr::Add(a, b) => {
+                let (a0, a1) = a.range();
+                let (b0, b1) = b.range();
+                (a0 + b0, a1 + b1)
+            }
+            Expr::Mul(a, b) => {
+                let (a0, a1) = a.range();
+                let (b0, b1) = b.range();
+                let products = [a0 * b0, a0 * b1, a1 * b0, a1 * b1];
+                (
+                    *products.iter().min().unwrap(),
+                    *products.iter().max().unwrap(),
+                )
+            }
+            Expr::Div(a, b) => {
+                let (a0, a1) = a.range();
+                match b.as_ref() {
+                    Expr::Lit(d) if *d != 0 => {
+                        let quotients = [a0 / d, a1 / d];
+                        (
+                            *quotients.iter().min().unwrap(),
+                            *quotients.iter().max().unwrap(),
+                        )
+                    }
+                    _ => (a0, a1),
+                }
+            }
+            // MONAD only ever divides/mods a non-negative accumulator by a
+            // positive literal, so the result always falls in 0..m.
+            Expr::Mod(a, b) => match b.as_ref() {
+                Expr::Lit(m) if *m > 0 => (0, m - 1),
+                _ => a.range(),
+            },
+            Expr::Eql(_, _) => (0, 1),
+        }
+    }
+}
+
+/// Build an addition, folding `x + 0` and constant operands away instead
+/// of growing the tree.
+#[allow(dead_code)]
+fn make_add(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (Expr::Lit(x), Expr::Lit(y)) => Expr::Lit(x + y),
+        (Expr::Lit(0), _) => b,
+        (_, Expr::Lit(0)) => a,
+        _ => Expr::Add(Box::new(a), Box::new(b)),
+    }
+}
+
+/// Build a multiplication, folding `x * 0 -> 0`, `x * 1 -> x`, and constant
+/// operands away instead of growing the tree.
+#[allow(dead_code)]
+fn make_mul(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (Expr::Lit(x), Expr::Lit(y)) => Expr::Lit(x * y),
+        (Expr::Lit(0), _) | (_, Expr::Lit(0)) => Expr::Lit(0),
+        (Expr::Lit(1), _) => b,
+        (_, Expr::Lit(1)) => a,
+        _ => Expr::Mul(Box::new(a), Box::new(b)),
+    }
+}
+
+/// Build a division, folding `x / 1 -> x` and constant operands away
+/// instead of growing the tree.
+#[allow(dead_code)]
+fn make_div(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (Expr::Lit(x), Expr::Lit(y)) => Expr::Lit(x / y),
+        (_, Expr::Lit(1)) => a,
+        _ => Expr::Div(Box::new(a), Box::new(b)),
+    }
+}
+
+/// Build a modulo, folding constant operands away and collapsing to the
+/// dividend when its range already sits below the modulus (i.e. the `mod`
+/// can never change its value).
+#[allow(dead_code)]
+fn make_mod(a: Expr, b: Expr) -> Expr {
+    match (&a, &b) {
+        (Expr::Lit(x), Expr::Lit(y)) => Expr::Lit(x % y),
+        (_, Expr::Lit(m)) if a.range().1 < *m => a,
+        _ => Expr::Mod(Box::new(a), Box::new(b)),
+    }
+}
+
+/// Build an equality check, collapsing to `Lit(1)`/`Lit(0)` whenever the
+/// answer is already known: both sides are the same literal, or their
+/// value ranges don't overlap at all.
+#[allow(dead_code)]
+fn make_eql(a: Expr, b: Expr) -> Expr {
+    if let (Expr::Lit(x), Expr::Lit(y)) = (&a, &b) {
+        return Expr::Lit(if x == y { 1 } else { 0 });
+    }
+
+    let (a_min, a_max) = a.range();
+    let (b_min, b_max) = b.range();
+    if a_max < b_min || b_max < a_min {
+        return Expr::Lit(0);
+    }
+
+    Expr::Eql(Box::new(a), Box::new(b))
+}
+
+/// The register index `Alu::var`/`Alu::set_var` map each variable name to.
+#[allow(dead_code)]
+fn var_slot(var: char) -> usize {
+    match var {
+        'w' => 0,
+        'x' => 1,
+        'y' => 2,
+        'z' => 3,
+        _ => panic!("Invalid variable: {}", var),
+    }
+}
+
+#[allow(dead_code)]
+fn resolve_symbolic(vars: &[Expr; 4], param: Param) -> Expr {
+    match param {
+        Param::Var(v) => vars[var_slot(v)].clone(),
+        Param::Num(n) => Expr::Lit(n),
+    }
+}
+
+/// Symbolically execute `program`, building an `Expr` tree per register
+/// instead of running on concrete numbers. Mirrors `Alu::execute`'s match
+/// arms one-for-one; the only new behavior is that `Inp` allocates a fresh
+/// `Expr::Input(k)` rather than reading a concrete value.
+#[allow(dead_code)]
+fn execute_symbolic(program: &[Instruction]) -> [Expr; 4] {
+    let mut vars = [Expr::Lit(0), Expr::Lit(0), Expr::Lit(0), Expr::Lit(0)];
+    let mut next_input = 0;
+
+    for instruction in program {
+        match *instruction {
+            Instruction::Inp(a) => {
+                vars[var_slot(a)] = Expr::Input(next_input);
+                next_input += 1;
+            }
+            Instruction::Add(a, b) => {
+                let rhs = resolve_symbolic(&vars, b);
+                vars[var_slot(a)] = make_add(vars[var_slot(a)].clone(), rhs);
+            }
+            Instruction::Mul(a, b) => {
+                let rhs = resolve_symbolic(&vars, b);
+                vars[var_slot(a)] = make_mul(vars[var_slot(a)].clone(), rhs);
+            }
+            Instruction::Div(a, b) => {
+                let rhs = resolve_symbolic(&vars, b);
+                vars[var_slot(a)] = make_div(vars[var_slot(a)].clone(), rhs);
+            }
+            Instruction::Mod(a, b) => {
+                let rhs = resolve_symbolic(&vars, b);
+                vars[var_slot(a)] = make_mod(vars[var_slot(a)].clone(), rhs);
+            }
+            Instruction::Eql(a, b) => {
+                let rhs = resolve_symbolic(&vars, b);
+                vars[var_slot(a)] = make_eql(vars[var_slot(a)].clone(), rhs);
+            }
+        }
+    }
+
+    vars
+}
+
 impl<'a> Alu<'a> {
     fn new(input: &'a [Int], program: &'a [Instruction], cache: &'a mut Cache) -> Self {
         let mut alu = Alu {
@@ -194,7 +494,10 @@ impl<'a> Alu<'a> {
         //
         // println!("Starting from pc = {}", self.pc);
         while self.pc < self.program.len() {
-            print!("{}. {:?} \t->\t {:?} \t->\t", self.pc, self.vars, self.program[self.pc]);
+            print!(
+                "{}. {:?} \t->\t {:?} \t->\t",
+                self.pc, self.vars, self.program[self.pc]
+            );
             self.execute(self.program[self.pc]);
             println!("{:?}", self.vars);
             self.pc += 1;
@@ -271,7 +574,7 @@ pub fn main_2() {
 
 /// Approach 3: Reverse engineering the 'z' register
 /// ------------------------------------------------
-/// We need to look for inputs that set the z register to zero. 
+/// We need to look for inputs that set the z register to zero.
 /// Perhaps there's a way to find clues in the code to figure out what variance leads to having
 /// zero in the z register.
 ///
@@ -306,7 +609,7 @@ pub fn main_2() {
 ///
 /// There are only 3 values different in every part of the program, also the mod x 26 is weird.
 /// Anyway, the invariants to the program are the input digit, the state of the registers, and
-/// those 3 values that can be different. 
+/// those 3 values that can be different.
 ///
 /// If we can figure out which state we need from the input and registers, in order to have z be
 /// zero, we can reverse engineer the program.
@@ -314,11 +617,12 @@ pub fn main_2() {
 /// - w is only written to by inp
 /// - z is a carry
 /// - x and y are set to zero each iteration.
-/// so we basically have a program with 3 parameters (those varying values) which has inputs 
+/// so we basically have a program with 3 parameters (those varying values) which has inputs
 /// (digit, carry) and has a certain output z.
 ///
 /// if we can figure out how manipulating the digit and the parameters affects z, we have a chance
 /// at simplifying the program.
+#[allow(dead_code)]
 fn subprog(a: Int, b: Int, c: Int) -> Vec<Instruction> {
     vec![
         Instruction::Inp('w'),
@@ -342,11 +646,12 @@ fn subprog(a: Int, b: Int, c: Int) -> Vec<Instruction> {
     ]
 }
 
+#[allow(dead_code)]
 fn sub_solutions(search_z: Int, a: Int, b: Int, c: Int) -> Vec<(i64, Int)> {
     let mut solutions = Vec::new();
     for w in 1..=9 {
         // inp w
- 
+
         // mul x 0
         // add x z
         // mod x 26
@@ -394,7 +699,10 @@ fn sub_solutions(search_z: Int, a: Int, b: Int, c: Int) -> Vec<(i64, Int)> {
         // ----------- z = orig_z / (a) ------> 1 or 26
         let orig_z_if_x_0 = z_if_x_0 * (a);
         let orig_z_if_x_1 = z_if_x_1 * (a);
-        println!("orig_z_if_x_0: {}, orig_z_if_x_1: {}", orig_z_if_x_0, orig_z_if_x_1);
+        println!(
+            "orig_z_if_x_0: {}, orig_z_if_x_1: {}",
+            orig_z_if_x_0, orig_z_if_x_1
+        );
         //
         // ----------- if (orig_z % 26) + (b) == w, then x = 0 -- leads to
         let orig_z_mod_26_if_x_0 = w - b;
@@ -411,62 +719,143 @@ fn sub_solutions(search_z: Int, a: Int, b: Int, c: Int) -> Vec<(i64, Int)> {
 
         // If a == 26, we can control z after setting x separately by multiplying by 26
         // ... So z is actually 26d + e if a == 26
-        // And e controls the digit and d the z output. 
+        // And e controls the digit and d the z output.
         // e = (w - b)
         // d = search_z * 26
 
         // if a == 1, z after setting x, is z
-        // 
+        //
 
         // If x = 0, z stays z
         // If x = 1, z is z * 26 + c*w
 
-
         // If a == 1, out_z = z*26 - c*w
 
         // reserve
     }
-    println!("search_z: {} ABC: {},{},{} solutions: {:?}", search_z, a, b, c, solutions);
+    println!(
+        "search_z: {} ABC: {},{},{} solutions: {:?}",
+        search_z, a, b, c, solutions
+    );
     solutions
 }
 
-pub fn main() {
-    let progs = vec![
-        (1, 13, 5),
-        (1, 15, 14),
-        (1, 15, 15),
-        (1, 11, 16),
-        (26, -16, 8),
-        (26, -11, 9),
-        (26, -6, 2),
-        (1, 11, 13),
-        (1, 10, 16),
-        (26, -10, 6),
-        (26, -8, 6),
-        (26, -11, 9),
-        (1, 12, 11),
-        (26,-15,5)
-    ];
-
-    let mut step = progs.len() - 1;
-    let search_z = 0;
-
-    let prog = progs[step - 1];
-    let solutions = sub_solutions(24 + (10 * 26), prog.0, prog.1, prog.2);
-
-    let mut i = 1;
-    for (digit, z) in solutions {
-        let input = create_input(digit);
-        let mut cache = Cache::new();
-        let program = subprog(prog.0, prog.1, prog.2);
+/// Split `program` into its 14 per-digit blocks, one per `Inp` instruction.
+fn split_blocks(program: &[Instruction]) -> Vec<&[Instruction]> {
+    let mut starts: Vec<usize> = program
+        .iter()
+        .enumerate()
+        .filter(|(_, i)| matches!(i, Instruction::Inp(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+    starts.push(program.len());
+    starts.windows(2).map(|w| &program[w[0]..w[1]]).collect()
+}
 
-        let mut alu = Alu::new(&input, &program, &mut cache);
-        alu.vars[3] = z;
-        println!("{}. in:{} / z:{} ---> ", i, digit, alu.vars[3]);
+/// Read the three constants that vary between `block`s (see `subprog`):
+/// the `div z` operand `a`, the `add x` operand `b` that feeds `eql x w`,
+/// and the `add y` operand `c` that follows `add y w`.
+fn extract_block_params(block: &[Instruction]) -> (Int, Int, Int) {
+    let a = block
+        .iter()
+        .find_map(|i| match i {
+            Instruction::Div('z', Param::Num(n)) => Some(*n),
+            _ => None,
+        })
+        .expect("block has no `div z <n>` instruction");
+
+    let b = block
+        .windows(2)
+        .find_map(|w| match w {
+            [Instruction::Add('x', Param::Num(n)), Instruction::Eql('x', Param::Var('w'))] => {
+                Some(*n)
+            }
+            _ => None,
+        })
+        .expect("block has no `add x <n>` before `eql x w`");
+
+    let c = block
+        .windows(2)
+        .find_map(|w| match w {
+            [Instruction::Add('y', Param::Var('w')), Instruction::Add('y', Param::Num(n))] => {
+                Some(*n)
+            }
+            _ => None,
+        })
+        .expect("block has no `add y w` followed by `add y <n>`");
+
+    (a, b, c)
+}
+
+fn digits_to_serial(digits: &[Int; 14]) -> i64 {
+    digits.iter().fold(0i64, |acc, &d| acc * 10 + d as i64)
+}
+
+/// Derive the 14 `(a, b, c)` block parameters straight from `program` and
+/// solve the digit-pairing constraints they impose, instead of hardcoding
+/// `progs` and reverse-engineering one block at a time by hand.
+///
+/// Each block pushes or pops a base-26 "stack" held in `z`: a block with
+/// `a == 1` always pushes `digit + c`, while a block with `a == 26` pops
+/// the most recently pushed value and only avoids re-pushing when
+/// `digit == peek + b`. Pairing each pop with its matching push (via
+/// ordinary stack discipline over the 14 blocks) ties their digits
+/// together as `digit[pop] = digit[push] + c[push] + b[pop]`. For the
+/// maximum we set the push digit as high as possible, for the minimum as
+/// low as possible, while keeping both digits in `1..=9`. Returns
+/// `(max_serial, min_serial)`, each validated by running it through the
+/// real `Alu` and checking that `z` comes out at zero.
+fn solve_serials(program: &[Instruction]) -> (i64, i64) {
+    let blocks: Vec<(Int, Int, Int)> = split_blocks(program)
+        .iter()
+        .map(|block| extract_block_params(block))
+        .collect();
+
+    let mut max_digits = [0 as Int; 14];
+    let mut min_digits = [0 as Int; 14];
+    let mut stack: Vec<(usize, Int)> = Vec::new();
+
+    for (j, &(a, b, c)) in blocks.iter().enumerate() {
+        if a == 1 {
+            stack.push((j, c));
+            continue;
+        }
+
+        let (i, c_push) = stack.pop().expect("pop block with an empty push stack");
+        let diff = c_push + b;
+
+        let max_push = (9 - diff).min(9);
+        max_digits[i] = max_push;
+        max_digits[j] = max_push + diff;
+
+        let min_push = (1 - diff).max(1);
+        min_digits[i] = min_push;
+        min_digits[j] = min_push + diff;
+    }
+
+    let max_serial = digits_to_serial(&max_digits);
+    let min_serial = digits_to_serial(&min_digits);
+
+    for serial in [max_serial, min_serial] {
+        let input = create_input(serial);
+        let mut cache = Cache::new();
+        let mut alu = Alu::new(&input, program, &mut cache);
         alu.run();
-        println!("<----- z: {}", alu.vars[3]);
-        i += 1;
+        assert_eq!(
+            alu.vars[3], 0,
+            "serial {} did not validate against the ALU",
+            serial
+        );
     }
+
+    (max_serial, min_serial)
+}
+
+pub fn main() {
+    let program = parse(include_str!("../../input/day24.txt"));
+    let (max_serial, min_serial) = solve_serials(&program);
+    println!("Part 1: {}", max_serial);
+    println!("Part 2: {}", min_serial);
 }
 
 #[cfg(test)]
@@ -477,7 +866,7 @@ mod test {
     /// inp a - Read an input value and write it to variable a.
     #[test]
     fn test_inp() {
-        let program = vec![Instruction::Inp('x')];
+        let program = alu! { inp x };
         let input = vec![5];
         let mut cache = Cache::new();
         let mut alu = Alu::new(&input, &program, &mut cache);
@@ -488,7 +877,7 @@ mod test {
     /// Test add instruction
     #[test]
     fn test_add() {
-        let program = vec![Instruction::Inp('x'), Instruction::Add('x', Param::Num(3))];
+        let program = alu! { inp x; add x 3 };
         let input = vec![5];
         let mut cache = Cache::new();
 
@@ -500,7 +889,7 @@ mod test {
     /// Test mul instruction
     #[test]
     fn test_mul() {
-        let program = vec![Instruction::Inp('x'), Instruction::Mul('x', Param::Num(3))];
+        let program = alu! { inp x; mul x 3 };
         let input = vec![5];
         let mut cache = Cache::new();
         let mut alu = Alu::new(&input, &program, &mut cache);
@@ -511,7 +900,7 @@ mod test {
     /// Test div instruction
     #[test]
     fn test_div() {
-        let program = vec![Instruction::Inp('x'), Instruction::Div('x', Param::Num(2))];
+        let program = alu! { inp x; div x 2 };
         let input = vec![7];
         let mut cache = Cache::new();
         let mut alu = Alu::new(&input, &program, &mut cache);
@@ -522,7 +911,7 @@ mod test {
     /// Test mod instruction
     #[test]
     fn test_mod() {
-        let program = vec![Instruction::Inp('x'), Instruction::Mod('x', Param::Num(2))];
+        let program = alu! { inp x; mod x 2 };
         let input = vec![5];
         let mut cache = Cache::new();
         let mut alu = Alu::new(&input, &program, &mut cache);
@@ -533,7 +922,7 @@ mod test {
     /// Test eql instruction
     #[test]
     fn test_eql() {
-        let program = vec![Instruction::Inp('x'), Instruction::Eql('x', Param::Num(7))];
+        let program = alu! { inp x; eql x 7 };
         let input = vec![7];
         let mut cache = Cache::new();
         let mut alu = Alu::new(&input, &program, &mut cache);
@@ -617,4 +1006,148 @@ mod w 2"#;
         assert_eq!(alu.vars[2], 1);
         assert_eq!(alu.vars[3], 1);
     }
+
+    #[test]
+    fn test_expr_range() {
+        assert_eq!(Expr::Input(0).range(), (1, 9));
+        assert_eq!(Expr::Lit(5).range(), (5, 5));
+        assert_eq!(make_add(Expr::Input(0), Expr::Input(1)).range(), (2, 18));
+    }
+
+    #[test]
+    fn test_make_add_constant_folds_and_identity() {
+        assert_eq!(make_add(Expr::Lit(3), Expr::Lit(4)), Expr::Lit(7));
+        assert_eq!(make_add(Expr::Lit(0), Expr::Input(0)), Expr::Input(0));
+        assert_eq!(make_add(Expr::Input(0), Expr::Lit(0)), Expr::Input(0));
+    }
+
+    #[test]
+    fn test_make_mul_constant_folds_and_identities() {
+        assert_eq!(make_mul(Expr::Lit(3), Expr::Lit(4)), Expr::Lit(12));
+        assert_eq!(make_mul(Expr::Input(0), Expr::Lit(0)), Expr::Lit(0));
+        assert_eq!(make_mul(Expr::Lit(0), Expr::Input(0)), Expr::Lit(0));
+        assert_eq!(make_mul(Expr::Input(0), Expr::Lit(1)), Expr::Input(0));
+        assert_eq!(make_mul(Expr::Lit(1), Expr::Input(0)), Expr::Input(0));
+    }
+
+    #[test]
+    fn test_make_div_constant_folds_and_identity() {
+        assert_eq!(make_div(Expr::Lit(7), Expr::Lit(2)), Expr::Lit(3));
+        assert_eq!(make_div(Expr::Input(0), Expr::Lit(1)), Expr::Input(0));
+    }
+
+    #[test]
+    fn test_make_mod_constant_folds_and_collapses() {
+        assert_eq!(make_mod(Expr::Lit(7), Expr::Lit(2)), Expr::Lit(1));
+        // Input's range (1..=9) is entirely below the modulus, so the mod
+        // can never change the value and collapses to the dividend.
+        assert_eq!(make_mod(Expr::Input(0), Expr::Lit(26)), Expr::Input(0));
+    }
+
+    #[test]
+    fn test_make_eql_literal_equality() {
+        assert_eq!(make_eql(Expr::Lit(5), Expr::Lit(5)), Expr::Lit(1));
+        assert_eq!(make_eql(Expr::Lit(5), Expr::Lit(3)), Expr::Lit(0));
+    }
+
+    #[test]
+    fn test_make_eql_disjoint_ranges_collapse_to_zero() {
+        // Input's range (1..=9) never reaches 20, so the comparison is
+        // statically known to be false.
+        assert_eq!(make_eql(Expr::Input(0), Expr::Lit(20)), Expr::Lit(0));
+    }
+
+    #[test]
+    fn test_execute_symbolic_allocates_one_input_per_inp() {
+        let program = parse("inp w\ninp x");
+        let vars = execute_symbolic(&program);
+        assert_eq!(vars[0], Expr::Input(0));
+        assert_eq!(vars[1], Expr::Input(1));
+    }
+
+    #[test]
+    fn test_execute_symbolic_div_by_one_collapses_to_dividend() {
+        let program = parse("inp z\ndiv z 1");
+        let vars = execute_symbolic(&program);
+        assert_eq!(vars[3], Expr::Input(0));
+    }
+
+    #[test]
+    fn test_execute_symbolic_mod_collapses_when_below_modulus() {
+        let program = parse("inp w\nmod w 26");
+        let vars = execute_symbolic(&program);
+        assert_eq!(vars[0], Expr::Input(0));
+    }
+
+    /// Mirrors the shape of the real puzzle's repeated blocks: a digit
+    /// compared against another digit shifted far out of its range. The
+    /// `eql` should resolve statically instead of staying symbolic.
+    #[test]
+    fn test_execute_symbolic_eql_collapses_with_disjoint_ranges() {
+        let program = parse("inp w\ninp x\nadd x 1000\neql x w");
+        let vars = execute_symbolic(&program);
+        assert_eq!(vars[1], Expr::Lit(0));
+    }
+
+    /// The `alu!` mnemonic form should build exactly the same program as
+    /// `subprog`'s hand-written `Instruction` literals, negative operands
+    /// included.
+    #[test]
+    fn test_alu_macro_matches_subprog() {
+        let program = alu! {
+            inp w;
+            mul x 0;
+            add x z;
+            mod x 26;
+            div z 26;
+            add x -16;
+            eql x w;
+            eql x 0;
+            mul y 0;
+            add y 25;
+            mul y x;
+            add y 1;
+            mul z y;
+            mul y 0;
+            add y w;
+            add y 8;
+            mul y x;
+            add z y;
+        };
+
+        assert_eq!(program, subprog(26, -16, 8));
+    }
+
+    #[test]
+    fn test_extract_block_params_matches_original_tuple() {
+        let block = subprog(26, -4, 7);
+        assert_eq!(extract_block_params(&block), (26, -4, 7));
+    }
+
+    #[test]
+    fn test_split_blocks_splits_on_inp() {
+        let program = [subprog(1, 13, 5), subprog(26, -13, 5)].concat();
+        let blocks = split_blocks(&program);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].len(), 18);
+        assert_eq!(blocks[1].len(), 18);
+    }
+
+    /// A push block (`a == 1`) immediately followed by its matching pop
+    /// block (`a == 26`) ties their digits together; both the max and min
+    /// serials `solve_serials` derives for this pair should run the real
+    /// `Alu` to `z == 0`.
+    #[test]
+    fn test_solve_serials_two_block_pair_validates_against_alu() {
+        let program = [subprog(1, 13, 5), subprog(26, -8, 7)].concat();
+        let (max_serial, min_serial) = solve_serials(&program);
+
+        for serial in [max_serial, min_serial] {
+            let input = create_input(serial);
+            let mut cache = Cache::new();
+            let mut alu = Alu::new(&input, &program, &mut cache);
+            alu.run();
+            assert_eq!(alu.vars[3], 0);
+        }
+    }
 }
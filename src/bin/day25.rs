@@ -1,298 +1,383 @@
 use std::str::FromStr;
 
+use colored::Colorize;
+
+use aoc2021::solution::Solution;
+
 /// --- Day 25: Sea Cucumber ---
 /// We have moving sea cucumbers, either moving south or east. We need to find the number of moves
 /// it takes until all sea cucumbers stop moving.
-/// I don't have a clue how this is best implemented.
 ///
-/// Maybe first choose a representation of a tile in the map
-type Tile = u8;
-const EMPTY: Tile = 0;
-const EAST: Tile = 1;
-const SOUTH: Tile = 2;
-
-/// Most basic implementation of a map
+/// The grid is stored as two bit-packed occupancy layers (one per herd)
+/// instead of a `Vec` of per-cell tiles, so a whole herd moves in one pass
+/// over 64-bit words rather than cell by cell, and a step reports whether
+/// anything moved instead of requiring a separate full-grid `stopped()`
+/// scan beforehand. This intentionally doesn't reuse `aoc2021::grid::Grid`:
+/// that type stores one `T` per cell, which is exactly the per-cell
+/// representation packing into 64-bit words was meant to replace.
 #[derive(Debug, PartialEq)]
 struct Map {
-    pub size: (usize, usize),
-    pub tiles: Vec<Tile>,
+    width: usize,
+    height: usize,
+    /// Each row is padded up to a whole number of 64-bit words, so a
+    /// row's bits live at `row * words_per_row .. (row + 1) * words_per_row`
+    /// in `east`/`south`.
+    words_per_row: usize,
+    east: Vec<u64>,
+    south: Vec<u64>,
 }
 
 /// Parser
 impl FromStr for Map {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, ()> {
-        let mut tiles = Vec::new();
-        let mut size = (0, 0);
-        for line in s.lines() {
-            size.1 = line.len();
-            for c in line.chars() {
-                match c {
-                    '.' => tiles.push(EMPTY),
-                    '>' => tiles.push(EAST),
-                    'v' => tiles.push(SOUTH),
+        let lines: Vec<&str> = s.lines().collect();
+        let height = lines.len();
+        let width = lines[0].len();
+        let words_per_row = (width + 63) / 64;
+
+        let mut east = vec![0u64; height * words_per_row];
+        let mut south = vec![0u64; height * words_per_row];
+
+        for (r, line) in lines.iter().enumerate() {
+            for (c, ch) in line.chars().enumerate() {
+                let word = r * words_per_row + c / 64;
+                let bit = c % 64;
+                match ch {
+                    '.' => {}
+                    '>' => east[word] |= 1 << bit,
+                    'v' => south[word] |= 1 << bit,
                     _ => panic!("Invalid character in map"),
                 }
             }
-            size.0 += 1;
         }
-        Ok(Map { size, tiles })
+
+        Ok(Map {
+            width,
+            height,
+            words_per_row,
+            east,
+            south,
+        })
     }
 }
 
 impl std::fmt::Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for i in 0..self.size.0 {
-            for j in 0..self.size.1 {
-                let c = match self.tiles[i * self.size.1 + j] {
-                    EMPTY => '.',
-                    EAST => '>',
-                    SOUTH => 'v',
-                    _ => panic!("Invalid tile"),
+        for r in 0..self.height {
+            for c in 0..self.width {
+                let word = r * self.words_per_row + c / 64;
+                let bit = c % 64;
+                let east = (self.east[word] >> bit) & 1 == 1;
+                let south = (self.south[word] >> bit) & 1 == 1;
+                let ch = if east {
+                    '>'
+                } else if south {
+                    'v'
+                } else {
+                    '.'
                 };
-                write!(f, "{}", c)?;
+                write!(f, "{}", ch)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
-/// Implementation
 impl Map {
-    fn has_moves(&self, indices: &[usize], t: Tile) -> bool {
-        let len = indices.len();
-        for i in 0..len - 1 {
-            let current = self.tiles[indices[i]];
-            let next = self.tiles[indices[i + 1]];
-
-            if current == t && next == EMPTY {
-                return true;
+    fn row(layer: &[u64], words_per_row: usize, r: usize) -> &[u64] {
+        &layer[r * words_per_row..(r + 1) * words_per_row]
+    }
+
+    /// Zero out any bits at columns `>= width`: the padding bits that round
+    /// a row up to a whole number of words, which should never read as set.
+    fn mask_to_width(row: &mut [u64], width: usize) {
+        for (i, word) in row.iter_mut().enumerate() {
+            let bit_start = i * 64;
+            if bit_start >= width {
+                *word = 0;
+            } else {
+                let bits_in_word = (width - bit_start).min(64);
+                if bits_in_word < 64 {
+                    *word &= (1u64 << bits_in_word) - 1;
+                }
             }
         }
-        false
     }
 
-    fn col_stopped(&self, y: usize) -> bool {
-        let indices = self.col_indices(y);
-        !self.has_moves(&indices, SOUTH)
-    }
+    /// Rotate a row's bits left by one column (multiplying the row, read as
+    /// one big little-endian integer, by two), wrapping the bit that falls
+    /// off column `width - 1` back around to column `0`. Used to turn a
+    /// mask of source columns into the mask of columns they move into.
+    fn rotate_row_left(row: &[u64], width: usize) -> Vec<u64> {
+        let last_bit = (row[(width - 1) / 64] >> ((width - 1) % 64)) & 1;
+
+        let mut out = vec![0u64; row.len()];
+        let mut carry = 0u64;
+        for (i, word) in row.iter().enumerate() {
+            out[i] = (word << 1) | carry;
+            carry = word >> 63;
+        }
+        Self::mask_to_width(&mut out, width);
 
-    fn row_stopped(&self, x: usize) -> bool {
-        let indices = self.row_indices(x);
-        !self.has_moves(&indices, EAST)
+        if last_bit == 1 {
+            out[0] |= 1;
+        } else {
+            out[0] &= !1u64;
+        }
+        out
     }
 
-    fn stopped(&self) -> bool {
-        for i in 0..self.size.0 {
-            if !self.row_stopped(i) {
-                return false;
-            }
+    /// Rotate a row's bits right by one column (dividing by two), wrapping
+    /// the bit that falls off column `0` back around to column
+    /// `width - 1`. Result[c] is therefore occupied[c + 1] - exactly what a
+    /// `>` at column `c` needs to check before it can move there.
+    fn rotate_row_right(row: &[u64], width: usize) -> Vec<u64> {
+        let first_bit = row[0] & 1;
+
+        let mut out = vec![0u64; row.len()];
+        let mut carry = 0u64; // the next-higher word's dropped LSB, fed into this word's top bit
+        for i in (0..row.len()).rev() {
+            let word = row[i];
+            out[i] = (word >> 1) | (carry << 63);
+            carry = word & 1;
         }
-        for i in 0..self.size.1 {
-            if !self.col_stopped(i) {
-                return false;
-            }
+
+        if first_bit == 1 {
+            out[(width - 1) / 64] |= 1 << ((width - 1) % 64);
+        } else {
+            out[(width - 1) / 64] &= !(1 << ((width - 1) % 64));
         }
-        true
+        out
     }
 
-    fn move_cucumbers(&mut self, indices: &[usize], direction: Tile) {
-        let len = indices.len();
-        if len < 2 {
-            return;  // Nothing to do if we have fewer than 2 indices
+    /// Move both herds one step, returning whether anything moved. Each
+    /// herd's move is computed from a snapshot of occupancy taken before
+    /// that herd moves: east resolves fully (against the occupancy at the
+    /// start of the step) before south is even considered (against the
+    /// occupancy once east has settled).
+    fn step(&mut self) -> bool {
+        let wpr = self.words_per_row;
+        let mut moved = false;
+
+        // --- East herd ---
+        for r in 0..self.height {
+            let east_row = Self::row(&self.east, wpr, r).to_vec();
+            let south_row = Self::row(&self.south, wpr, r);
+            let occupied: Vec<u64> = east_row.iter().zip(south_row).map(|(e, s)| e | s).collect();
+            let occupied_ahead = Self::rotate_row_right(&occupied, self.width);
+
+            let can_move: Vec<u64> = east_row
+                .iter()
+                .zip(&occupied_ahead)
+                .map(|(e, o)| e & !o)
+                .collect();
+
+            if can_move.iter().any(|&w| w != 0) {
+                moved = true;
+                let dest = Self::rotate_row_left(&can_move, self.width);
+                let start = r * wpr;
+                for i in 0..wpr {
+                    self.east[start + i] = (self.east[start + i] & !can_move[i]) | dest[i];
+                }
+            }
         }
 
-        let mut swaps: Vec<(usize, usize)> = Vec::new();
-
-        let mut i = 0;
-        while i < len - 1 {  // -1 because the last index is the same as the first
-            let current = indices[i];
-            let next = indices[i + 1];
-            
-            if self.tiles[current] == direction && self.tiles[next] == EMPTY {
-                swaps.push((current, next));
-                i += 2;  // Skip the next position as we've just queued a move there
-            } else {
-                i += 1;
+        // --- South herd ---
+        // Snapshot the combined (post-east) occupancy of every row up
+        // front, since all south cucumbers decide whether they can move
+        // against the grid as it stood before any of them moved.
+        let occupied_before: Vec<Vec<u64>> = (0..self.height)
+            .map(|r| {
+                Self::row(&self.east, wpr, r)
+                    .iter()
+                    .zip(Self::row(&self.south, wpr, r))
+                    .map(|(e, s)| e | s)
+                    .collect()
+            })
+            .collect();
+
+        let can_move_south: Vec<Vec<u64>> = (0..self.height)
+            .map(|r| {
+                let next = (r + 1) % self.height;
+                Self::row(&self.south, wpr, r)
+                    .iter()
+                    .zip(&occupied_before[next])
+                    .map(|(s, o)| s & !o)
+                    .collect()
+            })
+            .collect();
+
+        for (r, can_move) in can_move_south.iter().enumerate() {
+            if can_move.iter().any(|&w| w != 0) {
+                moved = true;
+                let next = (r + 1) % self.height;
+                for i in 0..wpr {
+                    self.south[r * wpr + i] &= !can_move[i];
+                    self.south[next * wpr + i] |= can_move[i];
+                }
             }
         }
 
-        // Now perform all the swaps
-        for (from, to) in swaps {
-            self.tiles.swap(from, to);
-        }
+        moved
     }
 
-    // Updated helper functions to include the first index at the end
-    fn row_indices(&self, x: usize) -> Vec<usize> {
-        let mut indices: Vec<usize> = (0..self.size.1).map(|y| x * self.size.1 + y).collect();
-        indices.push(indices[0]); // Add the first index to the end
-        indices
+    /// Run steps until the herds stop moving, returning the step number at
+    /// which that happened (the convention used by the puzzle: the first
+    /// step where nothing moves, one past the last step that did move
+    /// anything).
+    fn solve(&mut self) -> usize {
+        let mut steps = 0;
+        loop {
+            steps += 1;
+            if !self.step() {
+                break;
+            }
+        }
+        steps
     }
 
-    fn col_indices(&self, y: usize) -> Vec<usize> {
-        let mut indices: Vec<usize> = (0..self.size.0).map(|x| x * self.size.1 + y).collect();
-        indices.push(indices[0]); // Add the first index to the end
-        indices
+    /// Print the grid with `>` and `v` colored by herd and `.` dimmed, the
+    /// same way Day 11 colors its energy levels.
+    fn render(&self) {
+        for r in 0..self.height {
+            for c in 0..self.width {
+                let word = r * self.words_per_row + c / 64;
+                let bit = c % 64;
+                let east = (self.east[word] >> bit) & 1 == 1;
+                let south = (self.south[word] >> bit) & 1 == 1;
+                if east {
+                    print!("{}", ">".yellow());
+                } else if south {
+                    print!("{}", "v".cyan());
+                } else {
+                    print!("{}", ".".dimmed());
+                }
+            }
+            println!();
+        }
     }
 
-    // Updated mov_row and mov_col functions
-    fn mov_row(&mut self, x: usize) {
-        let indices = self.row_indices(x);
-        self.move_cucumbers(&indices, EAST);
-    }
+    /// Like `solve`, but clears the screen and calls `render` every `every`
+    /// steps (and once more at the fixed point), so the herds can be
+    /// watched congealing instead of only reporting the final step count.
+    fn solve_visualized(&mut self, every: usize) -> usize {
+        let mut steps = 0;
+        loop {
+            steps += 1;
+            let moved = self.step();
+
+            if steps % every == 0 || !moved {
+                print!("\x1B[2J\x1B[H");
+                println!("After step {}:", steps);
+                self.render();
+            }
 
-    fn mov_col(&mut self, y: usize) {
-        let indices = self.col_indices(y);
-        self.move_cucumbers(&indices, SOUTH);
+            if !moved {
+                break;
+            }
+        }
+        steps
     }
+}
 
-    fn mov(&mut self) {
-        for i in 0..self.size.0 {
-            self.mov_row(i);
-        }
-        for i in 0..self.size.1 {
-            self.mov_col(i);
-        }
+pub struct Day25;
+
+impl Solution for Day25 {
+    fn part1(&self, input: &str) -> aoc2021::solution::Result<String> {
+        let mut map = Map::from_str(input).map_err(|_| "failed to parse map")?;
+        Ok(map.solve().to_string())
     }
 
-    fn solve(&mut self) -> i32 {
-        let mut moves = 0;
-        while !self.stopped() {
-            self.mov();
-            moves += 1;
-        }
-        moves + 1
+    // Day 25 has no second puzzle to solve: the final star is awarded for
+    // free once every other day's two stars are in.
+    fn part2(&self, _input: &str) -> aoc2021::solution::Result<String> {
+        Ok("Merry Christmas!".to_string())
     }
 }
 
-// ----------------
 pub fn main() {
     let input = include_str!("../../input/day25.txt");
-    let mut map = Map::from_str(input).unwrap();
-    let moves = map.solve();
-    println!("Number of moves until all cucumbers stopped: {}", moves);
+
+    if std::env::args().any(|arg| arg == "--visualize") {
+        let mut map = Map::from_str(input).unwrap();
+        let steps = map.solve_visualized(1);
+        println!("Number of moves until all cucumbers stopped: {}", steps);
+        return;
+    }
+
+    match Day25.run(input) {
+        Ok(output) => println!("{}", output),
+        Err(e) => eprintln!("error: {}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[test]
-    fn test_map_from_str() {
-        let map = Map::from_str(r#"..>v."#).unwrap();
-        let map_exp = Map {
-            size: (1, 5),
-            tiles: vec![EMPTY, EMPTY, EAST, SOUTH, EMPTY],
-        };
-        assert_eq!(map, map_exp);
-    }
 
     #[test]
-    fn test_map_display() {
-        let map = Map::from_str(r#"..>v."#).unwrap();
-        let map_str = format!("{}", map);
-        assert_eq!(
-            map_str,
-            r#"..>v.
-"#
-        );
+    fn test_map_display_roundtrip() {
+        let input = "..>v.\n";
+        let map = Map::from_str(input).unwrap();
+        assert_eq!(format!("{}", map), input);
     }
 
     #[test]
-    fn test_row_stopped() {
-        let map = Map::from_str(r#"..>.."#).unwrap();
-        assert!(!map.row_stopped(0));
-
-        let map = Map::from_str(r#"..>v."#).unwrap();
-        assert!(
-            map.row_stopped(0),
-            "row should stop if a south cucumber blocks an east one"
-        );
-
-        let map = Map::from_str(r#"..>v>"#).unwrap();
-        assert!(!map.row_stopped(0), "row is not stopped if it can wrap");
-    }
+    fn test_step_moves_east_cucumber() {
+        let mut map = Map::from_str("..>..\n").unwrap();
+        let expected = Map::from_str("...>.\n").unwrap();
 
-    #[test]
-    fn test_col_stopped() {
-        let map = Map::from_str(
-            r#".....
-..v..
-....."#,
-        )
-        .unwrap();
-        assert!(!map.col_stopped(2));
-
-        let map = Map::from_str(
-            r#".....
-..v..
-..>.."#,
-        )
-        .unwrap();
-        assert!(
-            map.col_stopped(2),
-            "col should stop if an east cucumber blocks a south one"
-        );
-        let map = Map::from_str(
-            r#".....
-.....
-..v.."#,
-        )
-        .unwrap();
-        assert!(!map.col_stopped(2), "col is not stopped if it can wrap");
+        assert!(map.step());
+        assert_eq!(map, expected);
     }
 
     #[test]
-    fn test_map_stopped() {
-        let map = Map::from_str(
-            r#"..>>v>vv..
-..v.>>vv..
-..>>v>>vv.
-..>>>>>vv.
-v......>vv
-v>v....>>v
-vvv.....>>
->vv......>
-.>v.vv.v.."#,
-        )
-        .unwrap();
-        assert!(map.stopped());
-    }
+    fn test_step_east_wraps_around() {
+        let mut map = Map::from_str("....>\n").unwrap();
+        let expected = Map::from_str(">....\n").unwrap();
 
-    #[test]
-    fn test_row_indexes() {
-        let map = Map::from_str(r#"..>v."#).unwrap();
-        assert_eq!(map.row_indices(0), vec![0, 1, 2, 3, 4, 0]);
+        assert!(map.step());
+        assert_eq!(map, expected);
     }
 
     #[test]
-    fn test_col_indexes() {
-        let map = Map::from_str(
-            r#"..>v.
-.....
-....."#,
-        )
-        .unwrap();
-        assert_eq!(map.col_indices(2), vec![2, 7, 12, 2]);
+    fn test_step_blocked_cucumber_does_not_move() {
+        let mut map = Map::from_str("..>v.\n").unwrap();
+        let before = Map::from_str("..>v.\n").unwrap();
+
+        // the `v` blocks the `>` from moving east, and in a 1-row grid a
+        // `v` always wraps back into the row it's already in, so it's
+        // blocked by itself too: nothing moves this step.
+        assert!(!map.step());
+        assert_eq!(map, before);
     }
 
     #[test]
-    fn test_map_move1() {
-        let mut map = Map::from_str(r#"..>.."#).unwrap();
-        let map_exp = Map::from_str(r#"...>."#).unwrap();
+    fn test_step_south_wraps_around() {
+        let input = ".\nv\n";
+        let mut map = Map::from_str(input).unwrap();
+        let expected = Map::from_str("v\n.\n").unwrap();
 
-        map.mov_row(0);
-        assert_eq!(map, map_exp);
+        assert!(map.step());
+        assert_eq!(map, expected);
     }
 
     #[test]
-    fn test_map_move2() {
-        let mut map = Map::from_str(r#"....>"#).unwrap();
-        let map_exp = Map::from_str(r#">...."#).unwrap();
-
-        map.mov_row(0);
-        assert_eq!(map, map_exp);
+    fn test_map_stopped_after_one_more_step() {
+        let map_str = r#"..>>v>vv..
+..v.>>vv..
+..>>v>>vv.
+..>>>>>vv.
+v......>vv
+v>v....>>v
+vvv.....>>
+>vv......>
+.>v.vv.v..
+"#;
+        let mut map = Map::from_str(map_str).unwrap();
+        assert!(!map.step());
     }
 
     #[test]
@@ -312,7 +397,7 @@ v.v..>>v.v
     }
 
     #[test]
-    fn test_example2() {
+    fn test_example_steps() {
         let input = r#"v...>>.vv>
 .vv>>.vv..
 >>.>v>...v
@@ -323,7 +408,10 @@ v>v.vv.v..
 v.v..>>v.v
 ....v..v.>"#;
 
-        let cases = vec![(1, r#"....>.>v.>
+        let cases = vec![
+            (
+                1,
+                r#"....>.>v.>
 v.v>.>v.v.
 >v>>..>v..
 >>v>v>.>.v
@@ -331,8 +419,11 @@ v.v>.>v.v.
 v>>.>vvv..
 ..v...>>..
 vv...>>vv.
->.v.v..v.v"#),
-            (2, r#">.v.v>>..v
+>.v.v..v.v"#,
+            ),
+            (
+                2,
+                r#">.v.v>>..v
 v.v.>>vv..
 >v>.>.>.v.
 >>v>v.>v>.
@@ -340,20 +431,23 @@ v.v.>>vv..
 .>v>>.v.v.
 v....v>v>.
 .vv..>>v..
-v>.....vv."#)
+v>.....vv."#,
+            ),
         ];
-        for (moves, state) in cases {
+        for (steps, state) in cases {
             let mut map = Map::from_str(input).unwrap();
-            // do the moves
-            for _ in 0..moves {
-                map.mov();
+            for _ in 0..steps {
+                map.step();
             }
             let expected = Map::from_str(state).unwrap();
-            assert_eq!(expected, map, "after {} moves,\n{}\nshould be\n{}", moves, map, expected);
+            assert_eq!(
+                expected, map,
+                "after {} steps,\n{}\nshould be\n{}",
+                steps, map, expected
+            );
         }
     }
 
-
     #[test]
     fn test_last_steps_example() {
         let input = r#"..>>v>vv..
@@ -370,4 +464,36 @@ vvv.....>>
         assert_eq!(moves, 1);
     }
 
+    #[test]
+    fn test_solution_parts() {
+        let input = r#"v...>>.vv>
+.vv>>.vv..
+>>.>v>...v
+>>v>>.>.v.
+v>v.vv.v..
+>.>>..v...
+.vv..>.>v.
+v.v..>>v.v
+....v..v.>"#;
+        assert_eq!(Day25.part1(input).unwrap(), "58");
+        assert_eq!(Day25.part2(input).unwrap(), "Merry Christmas!");
+    }
+
+    #[test]
+    fn test_solve_visualized_matches_solve() {
+        let input = r#"v...>>.vv>
+.vv>>.vv..
+>>.>v>...v
+>>v>>.>.v.
+v>v.vv.v..
+>.>>..v...
+.vv..>.>v.
+v.v..>>v.v
+....v..v.>"#;
+        let mut visualized = Map::from_str(input).unwrap();
+        let mut plain = Map::from_str(input).unwrap();
+
+        assert_eq!(visualized.solve_visualized(7), plain.solve());
+        assert_eq!(visualized, plain);
+    }
 }
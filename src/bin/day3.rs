@@ -1,87 +1,119 @@
-use aoc2021::read_strs;
-
-fn gamma_rate(numbers: &[String]) -> (i32, i32) {
-    let mut result = (String::new(), String::new());
-    let positions = numbers[0].len();
-    for i in 0..positions {
-        let mut counts = vec![0; 2];
-        for number in numbers.iter() {
-            let digit = number.chars().nth(i).unwrap();
-            counts[digit as usize - '0' as usize] += 1;
-        }
-        if counts[0] > counts[1] {
-            result.0.push('0');
-            result.1.push('1');
-        } else {
-            result.0.push('1');
-            result.1.push('0');
-        }
-    }
-    (
-        i32::from_str_radix(result.0.as_str(), 2).unwrap(),
-        i32::from_str_radix(result.1.as_str(), 2).unwrap(),
-    )
+use aoc2021::solution::{Result, Solution};
+
+/// Which group `tree_filter` should keep at each bit position.
+enum Mode {
+    MostCommon,
+    LeastCommon,
 }
 
-fn day3() {
-    let numbers = read_strs("input/day3.txt");
-    let (gamma_rate, epsilon) = gamma_rate(&numbers);
-    println!("Gamma {}", gamma_rate);
-    println!("Epsil {}", epsilon);
-    println!("Power {}", gamma_rate * epsilon);
+/// Parse each line into a `u32` (read MSB-first, as written), returning the
+/// numbers alongside the bit width they were parsed at.
+fn parse_numbers(input: &str) -> (Vec<u32>, usize) {
+    let lines: Vec<&str> = input.lines().collect();
+    let width = lines[0].len();
+    let numbers = lines
+        .iter()
+        .map(|l| u32::from_str_radix(l, 2).unwrap())
+        .collect();
+    (numbers, width)
 }
 
-fn rating_finder(numbers: &[String], most_common: bool) -> i32 {
-    let mut pos = 0;
-    let mut candidates = numbers.to_owned();
-    while candidates.len() > 1 {
-        // find the most common digit in the current position
-        let mut counts = vec![0; 2];
-        for number in candidates.iter() {
-            let digit = number.chars().nth(pos).unwrap();
-            counts[digit as usize - '0' as usize] += 1;
-        }
-        // figure out which digit to search for
-        let digit;
-        if most_common {
-            digit = if counts[0] > counts[1] { '0' } else { '1' };
-        } else {
-            digit = if counts[0] > counts[1] { '1' } else { '0' };
+fn gamma_epsilon(numbers: &[u32], width: usize) -> (u32, u32) {
+    let mut gamma = 0;
+    for bit in (0..width).rev() {
+        let ones = numbers.iter().filter(|n| (*n >> bit) & 1 == 1).count();
+        let zeros = numbers.len() - ones;
+        if ones >= zeros {
+            gamma |= 1 << bit;
         }
+    }
+    let epsilon = gamma ^ ((1 << width) - 1);
+    (gamma, epsilon)
+}
+
+/// Partition `data` on bit `pos` into zeros and ones, keep the group `mode`
+/// selects (ties favor the `1` group for `MostCommon` and the `0` group for
+/// `LeastCommon`), and recurse on `pos - 1` until one number remains.
+fn tree_filter(data: &[u32], pos: usize, mode: &Mode) -> u32 {
+    if data.len() == 1 {
+        return data[0];
+    }
 
-        // remove all numbers that don't match the digit at
-        // the current position
-        candidates = candidates
-            .iter()
-            .filter(|number| number.chars().nth(pos).unwrap() == digit)
-            .cloned()
-            .collect();
+    let (ones, zeros): (Vec<u32>, Vec<u32>) = data.iter().partition(|n| (*n >> pos) & 1 == 1);
+    let keep = match mode {
+        Mode::MostCommon if ones.len() >= zeros.len() => ones,
+        Mode::MostCommon => zeros,
+        Mode::LeastCommon if zeros.len() <= ones.len() => zeros,
+        Mode::LeastCommon => ones,
+    };
 
-        pos += 1;
+    if pos == 0 {
+        return keep[0];
     }
 
-    i32::from_str_radix(candidates[0].as_str(), 2).unwrap()
+    tree_filter(&keep, pos - 1, mode)
 }
 
-fn ox_rating(numbers: &[String]) -> i32 {
-    rating_finder(numbers, true)
+fn ox_rating(numbers: &[u32], width: usize) -> u32 {
+    tree_filter(numbers, width - 1, &Mode::MostCommon)
 }
 
-fn co2_rating(numbers: &[String]) -> i32 {
-    rating_finder(numbers, false)
+fn co2_rating(numbers: &[u32], width: usize) -> u32 {
+    tree_filter(numbers, width - 1, &Mode::LeastCommon)
 }
 
-fn day3b() {
-    let numbers = read_strs("input/day3.txt");
-    let ox_rating = ox_rating(&numbers);
-    let co2_rating = co2_rating(&numbers);
+pub struct Day3;
 
-    println!("Ox  rating {}", ox_rating);
-    println!("CO2 rating {}", co2_rating);
-    println!("LS  rating {}", ox_rating * co2_rating);
+impl Solution for Day3 {
+    fn part1(&self, input: &str) -> Result<String> {
+        let (numbers, width) = parse_numbers(input);
+        let (gamma, epsilon) = gamma_epsilon(&numbers, width);
+        Ok((gamma * epsilon).to_string())
+    }
+
+    fn part2(&self, input: &str) -> Result<String> {
+        let (numbers, width) = parse_numbers(input);
+        let ox_rating = ox_rating(&numbers, width);
+        let co2_rating = co2_rating(&numbers, width);
+        Ok((ox_rating * co2_rating).to_string())
+    }
 }
 
 pub fn main() {
-    day3();
-    day3b();
+    let input = std::fs::read_to_string("input/day3.txt").expect("file not found");
+    match Day3.run(&input) {
+        Ok(output) => println!("{}", output),
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010";
+
+    #[test]
+    fn test_gamma_epsilon() {
+        let (numbers, width) = parse_numbers(EXAMPLE);
+        assert_eq!(gamma_epsilon(&numbers, width), (22, 9));
+    }
+
+    #[test]
+    fn test_ox_rating() {
+        let (numbers, width) = parse_numbers(EXAMPLE);
+        assert_eq!(ox_rating(&numbers, width), 23);
+    }
+
+    #[test]
+    fn test_co2_rating() {
+        let (numbers, width) = parse_numbers(EXAMPLE);
+        assert_eq!(co2_rating(&numbers, width), 10);
+    }
+
+    #[test]
+    fn test_solution_parts() {
+        assert_eq!(Day3.part1(EXAMPLE).unwrap(), "198");
+        assert_eq!(Day3.part2(EXAMPLE).unwrap(), "230");
+    }
 }
@@ -1,7 +1,8 @@
-use std::collections::HashMap;
 use std::io::BufRead;
 use std::{fs::File, io::BufReader};
 
+use aoc2021::grid::CoverageGrid;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 struct Position {
     x: i32,
@@ -68,60 +69,33 @@ fn read_lines(filename: &str) -> Vec<Line> {
     lines
 }
 
-fn day5() {
-    let lines = read_lines("input/day5.txt");
-
-    let mut vent_map: HashMap<Position, i32> = HashMap::new();
+/// Count the points covered by at least `threshold` lines, feeding each
+/// line's points into a shared `CoverageGrid`. `straight_only` replaces
+/// the old copy-pasted diagonal-skip: part one sets it to skip diagonal
+/// lines, part two doesn't.
+fn vent_overlap_count(lines: &[Line], straight_only: bool, threshold: usize) -> usize {
+    let mut grid = CoverageGrid::new();
 
     for line in lines {
-        if line.is_diagonal() {
-            println!("{:?} is diagonal, skip", line);
+        if straight_only && line.is_diagonal() {
             continue;
         }
 
-        let points_in_line = line.get_points();
-        for point in points_in_line {
-            println!("{:?} covers point {:?}", line, point);
-
-            // mark this point on the vent_map, increase if already present
-            let count = vent_map.entry(point).or_insert(0);
-            *count += 1;
-        }
+        grid.add_points(line.get_points().into_iter().map(|p| (p.x, p.y)));
     }
 
-    // now count the number of points that are covered by more than one line
-    let mut count = 0;
-    for (_, v) in vent_map {
-        if v > 1 {
-            count += 1;
-        }
-    }
+    grid.count_overlaps(threshold)
+}
 
+fn day5() {
+    let lines = read_lines("input/day5.txt");
+    let count = vent_overlap_count(&lines, true, 2);
     println!("{} points are covered by more than one line", count);
 }
 
 fn day5b() {
     let lines = read_lines("input/day5.txt");
-
-    let mut vent_map: HashMap<Position, i32> = HashMap::new();
-
-    for line in lines {
-        let points_in_line = line.get_points();
-        for point in points_in_line {
-            // mark this point on the vent_map, increase if already present
-            let count = vent_map.entry(point).or_insert(0);
-            *count += 1;
-        }
-    }
-
-    // now count the number of points that are covered by more than one line
-    let mut count = 0;
-    for (_, v) in vent_map {
-        if v > 1 {
-            count += 1;
-        }
-    }
-
+    let count = vent_overlap_count(&lines, false, 2);
     println!("{} points are covered by more than one line", count);
 }
 
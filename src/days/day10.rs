@@ -1,61 +1,121 @@
 use super::read_strs;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+/// A configurable opener/closer grammar for the stack-based balanced-
+/// delimiter engine below (`parse_line`, `LineState`). Maps each opener to
+/// its closer, plus the per-closer scores used for corrupt lines and for
+/// completion lines, so the same engine validates any grammar of
+/// single-character delimiters — including a quote whose opener and closer
+/// are the same character — not just the four AoC brackets. Every
+/// delimiter is one `char`; multi-character tokens such as tags are not
+/// matched.
+struct DelimiterSet {
+    closers: HashMap<char, char>,
+    corrupt_scores: HashMap<char, u64>,
+    completion_scores: HashMap<char, u64>,
+}
+
+impl DelimiterSet {
+    /// Builds a set from `(opener, closer, corrupt_score, completion_score)`
+    /// rows.
+    fn new(rows: &[(char, char, u64, u64)]) -> Self {
+        let mut closers = HashMap::new();
+        let mut corrupt_scores = HashMap::new();
+        let mut completion_scores = HashMap::new();
+        for &(opener, closer, corrupt_score, completion_score) in rows {
+            closers.insert(opener, closer);
+            corrupt_scores.insert(closer, corrupt_score);
+            completion_scores.insert(closer, completion_score);
+        }
+        DelimiterSet {
+            closers,
+            corrupt_scores,
+            completion_scores,
+        }
+    }
+
+    /// The four bracket pairs this puzzle uses, with their AoC day 10
+    /// corrupt/completion scores.
+    fn aoc_brackets() -> Self {
+        Self::new(&[
+            ('(', ')', 3, 1),
+            ('[', ']', 57, 2),
+            ('{', '}', 1197, 3),
+            ('<', '>', 25137, 4),
+        ])
+    }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    fn closer_for(&self, opener: char) -> Option<char> {
+        self.closers.get(&opener).copied()
+    }
+
+    fn corrupt_score(&self, closer: char) -> u64 {
+        self.corrupt_scores.get(&closer).copied().unwrap_or(0)
+    }
+
+    fn completion_score(&self, closer: char) -> u64 {
+        self.completion_scores.get(&closer).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 enum LineState {
     Valid,
-    Incomplete(u64),
+    /// The stack of closers (outermost first, innermost on top) still needed to finish the
+    /// line, as left behind by `parse_line`'s single scan. Kept as the raw
+    /// sequence rather than a pre-folded score so callers can also inspect
+    /// or display the actual completion string; `autocomplete_score` is
+    /// where that sequence turns into a number.
+    Incomplete(Vec<char>),
     Corrupt(char),
 }
 
 impl LineState {
-    pub fn get_score(&self) -> u64 {
+    pub fn get_score(&self, delimiters: &DelimiterSet) -> u64 {
         match self {
             LineState::Valid => 0,
-            LineState::Incomplete(score) => *score,
-            LineState::Corrupt(c) => match c {
-                ')' => 3,
-                ']' => 57,
-                '}' => 1197,
-                '>' => 25137,
-                _ => 0,
-            },
+            LineState::Incomplete(closers) => autocomplete_score(delimiters, closers),
+            LineState::Corrupt(c) => delimiters.corrupt_score(*c),
         }
     }
+}
 
-    pub fn calc_completion_score(closers: &[char]) -> u64 {
-        
-        closers.iter().rev().map(|c| match c {
-            ')' => 1,
-            ']' => 2,
-            '}' => 3,
-            '>' => 4,
-            _ => 0,
-        }).fold(0, |acc, x| (acc * 5) + x)
-    }
+/// Folds a line's remaining closer stack (as produced by `parse_line`, read
+/// from the top down) into its completion score.
+fn autocomplete_score(delimiters: &DelimiterSet, closers: &[char]) -> u64 {
+    closers
+        .iter()
+        .rev()
+        .map(|&c| delimiters.completion_score(c))
+        .fold(0, |acc, x| (acc * 5) + x)
 }
 
-fn parse_line(str: &str) -> LineState {
+/// Scans a line once against `delimiters`, tracking the stack of closers
+/// still owed. Returns `Corrupt` as soon as a character doesn't match the
+/// closer on top of the stack, `Incomplete` with whatever's left on the
+/// stack if the line runs out first, or `Valid` if the stack empties out
+/// exactly. A character equal to the closer on top of the stack closes it
+/// even when it is also an opener, so a same-character quote pair opens
+/// and then closes.
+fn parse_line(delimiters: &DelimiterSet, str: &str) -> LineState {
     let mut closers: Vec<char> = vec![];
     // loop through each character in the string
     for c in str.chars() {
-        match c {
-            '(' => closers.push(')'),
-            '[' => closers.push(']'),
-            '{' => closers.push('}'),
-            '<' => closers.push('>'),
-            _ => {
-                if let Some(last) = closers.pop() {
-                    if last != c {
-                        return LineState::Corrupt(c);
-                    }
-                } else {
-                    return LineState::Corrupt(c);
-                }
+        if closers.last() == Some(&c) {
+            closers.pop();
+        } else if let Some(closer) = delimiters.closer_for(c) {
+            closers.push(closer);
+        } else if let Some(last) = closers.pop() {
+            if last != c {
+                return LineState::Corrupt(c);
             }
+        } else {
+            return LineState::Corrupt(c);
         }
     }
     if !closers.is_empty() {
-        return LineState::Incomplete(LineState::calc_completion_score(&closers));
+        return LineState::Incomplete(closers);
     }
     LineState::Valid
 }
@@ -64,47 +124,46 @@ fn is_corrupt(state: LineState) -> bool {
     matches!(state, LineState::Corrupt(_))
 }
 
-pub fn day10() {
-    let lines = read_strs("input/day10.txt");
-
-    // print the number of total lines
-    println!("  lines: {}", lines.len());
-
-    let syntax_error_score: u64 = lines
+/// Sum of the per-character scores of every corrupt line (the first
+/// mismatched closer on each line), per `LineState::get_score`.
+fn syntax_error_score(delimiters: &DelimiterSet, lines: &[String]) -> u64 {
+    lines
         .iter()
-        .map(|l| parse_line(l))
-        .filter(|&s| matches!(s, LineState::Corrupt(_)))
-        .map(|s| s.get_score())
-        .sum();
-    println!("syntax error score: {:?}", syntax_error_score);
+        .map(|l| parse_line(delimiters, l))
+        .filter(|s| matches!(s, LineState::Corrupt(_)))
+        .map(|s| s.get_score(delimiters))
+        .sum()
 }
 
-pub fn day10b() {
-    let lines = read_strs("input/day10.txt");
-
-    // print the number of total lines
-    println!("  lines: {}", lines.len());
-
+/// The middle value (once sorted) of the completion scores of every
+/// incomplete line, or `None` if no line is incomplete.
+fn middle_completion_score(delimiters: &DelimiterSet, lines: &[String]) -> Option<u64> {
     let mut completion_scores: Vec<u64> = lines
         .iter()
-        .map(|l| parse_line(l))
-        .filter(|&s| matches!(s, LineState::Incomplete(_)))
-        .map(|s| s.get_score())
+        .map(|l| parse_line(delimiters, l))
+        .filter(|s| matches!(s, LineState::Incomplete(_)))
+        .map(|s| s.get_score(delimiters))
         .collect();
 
-
-    // sort the completion scores
     completion_scores.sort_unstable();
-    
-    println!("completion scores: {:?}", completion_scores);
+    completion_scores.get(completion_scores.len() / 2).copied()
+}
 
-    // get middle value of completion scores vector
-    let middle = completion_scores.len() / 2;
-    let middle_score = completion_scores[middle];
+pub fn day10() -> Result<String> {
+    let lines = read_strs("input/day10.txt");
+    let delimiters = DelimiterSet::aoc_brackets();
+    Ok(format!(
+        "syntax error score: {}",
+        syntax_error_score(&delimiters, &lines)
+    ))
+}
 
-    // print middle score
-    println!("middle score: {}", middle_score);
-        
+pub fn day10b() -> Result<String> {
+    let lines = read_strs("input/day10.txt");
+    let delimiters = DelimiterSet::aoc_brackets();
+    let middle_score = middle_completion_score(&delimiters, &lines)
+        .context("no incomplete lines to compute a middle score from")?;
+    Ok(format!("middle score: {}", middle_score))
 }
 
 #[cfg(test)]
@@ -114,10 +173,80 @@ mod tests {
 
     #[test]
     fn test_completion_score() {
-        assert_eq!(LineState::calc_completion_score(&[']', ')', '}', '>']), 294);
-        // assert_eq!(LineState::calc_completion_score(&[']']), 2);
-        // assert_eq!(LineState::calc_completion_score(&['}']), 3);
-        // assert_eq!(LineState::calc_completion_score(&['>']), 4);
+        let delimiters = DelimiterSet::aoc_brackets();
+        // the stack for the completion "])}>", innermost (`]`) on top
+        assert_eq!(autocomplete_score(&delimiters, &['>', '}', ')', ']']), 294);
+        // assert_eq!(autocomplete_score(&delimiters, &[']']), 2);
+        // assert_eq!(autocomplete_score(&delimiters, &['}']), 3);
+        // assert_eq!(autocomplete_score(&delimiters, &['>']), 4);
+    }
+
+    #[test]
+    fn test_parse_line_incomplete_exposes_closer_stack() {
+        let delimiters = DelimiterSet::aoc_brackets();
+        assert_eq!(
+            parse_line(&delimiters, "[({(<(())[]>[["),
+            LineState::Incomplete(vec![']', ')', '}', ')', ']', ']'])
+        );
+    }
+
+    fn example_lines() -> Vec<String> {
+        [
+            "[({(<(())[]>[[{[]{<()<>>",
+            "[(()[<>])]({[<{<<[]>>(",
+            "{([(<{}[<>[]}>{[]{[(<()>",
+            "(((({<>}<{<{<>}{[]{[]{}",
+            "[[<[([]))<([[{}[[()]]]",
+            "[{[{({}]{}}([{[{{{}}([]",
+            "{<[[]]>}<{[{[{[]{()[[[]",
+            "[<(<(<(<{}))><([]([]()",
+            "<{([([[(<>()){}]>(<<{{",
+            "<{([{{}}[<[[[<>{}]]]>[]]",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_syntax_error_score_matches_known_total() {
+        let delimiters = DelimiterSet::aoc_brackets();
+        assert_eq!(syntax_error_score(&delimiters, &example_lines()), 26397);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_middle_completion_score_matches_known_value() {
+        let delimiters = DelimiterSet::aoc_brackets();
+        assert_eq!(
+            middle_completion_score(&delimiters, &example_lines()),
+            Some(288957)
+        );
+    }
+
+    #[test]
+    fn test_middle_completion_score_none_when_all_valid_or_corrupt() {
+        let delimiters = DelimiterSet::aoc_brackets();
+        assert_eq!(
+            middle_completion_score(&delimiters, &["()".to_string()]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_delimiter_set_validates_a_single_pair_grammar() {
+        // A toy grammar of one pair with its own scores, in place of the
+        // four-pair AoC set.
+        let quotes = DelimiterSet::new(&[('<', '>', 1, 1)]);
+
+        assert_eq!(parse_line(&quotes, "<<>"), LineState::Incomplete(vec!['>']));
+        assert_eq!(parse_line(&quotes, "<>>"), LineState::Corrupt('>'));
+        assert_eq!(parse_line(&quotes, "<>"), LineState::Valid);
+    }
+
+    #[test]
+    fn test_delimiter_set_unknown_closer_scores_zero() {
+        let empty = DelimiterSet::new(&[]);
+        assert_eq!(empty.corrupt_score(')'), 0);
+        assert_eq!(empty.completion_score(')'), 0);
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use anyhow::Result;
+
 use super::read_strs;
 
 fn read_fish(path: &str) -> HashMap<u8, usize> {
@@ -68,3 +70,123 @@ pub fn day6() {
     // show number of fish
     print_fish(&fishes, "Final population")
 }
+
+/// Part 1 and 2 via `population_after` instead of `day6`'s printing,
+/// day-by-day `progress_fishes` simulation, so part 2's 256-day count
+/// doesn't have to be walked one day at a time.
+pub fn day6_all() -> Result<String> {
+    let fishes = read_fish("input/day6.txt");
+    Ok(format!(
+        "part1: {}\npart2: {}",
+        population_after(&fishes, 80),
+        population_after(&fishes, 256)
+    ))
+}
+
+type Matrix = [[u128; 9]; 9];
+
+fn identity_matrix() -> Matrix {
+    let mut m = [[0u128; 9]; 9];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = 1;
+    }
+    m
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0u128; 9]; 9];
+    for i in 0..9 {
+        for j in 0..9 {
+            let mut sum = 0u128;
+            for k in 0..9 {
+                sum += a[i][k] * b[k][j];
+            }
+            result[i][j] = sum;
+        }
+    }
+    result
+}
+
+fn matrix_pow(mut base: Matrix, mut exp: u64) -> Matrix {
+    let mut result = identity_matrix();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = multiply(&result, &base);
+        }
+        base = multiply(&base, &base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// The one-day transition: bucket `i` (1..=8) shifts down into bucket
+/// `i - 1`, and bucket 0 both resets into bucket 6 and spawns a new fish
+/// into bucket 8.
+fn transition_matrix() -> Matrix {
+    let mut m = [[0u128; 9]; 9];
+    for i in 0..8 {
+        m[i][i + 1] = 1;
+    }
+    m[6][0] = 1;
+    m[8][0] = 1;
+    m
+}
+
+fn bucket_vector(fishes: &HashMap<u8, usize>) -> [u128; 9] {
+    let mut v = [0u128; 9];
+    for (&timer, &count) in fishes {
+        v[timer as usize] = count as u128;
+    }
+    v
+}
+
+fn apply(matrix: &Matrix, v: &[u128; 9]) -> [u128; 9] {
+    let mut result = [0u128; 9];
+    for (i, row) in matrix.iter().enumerate() {
+        result[i] = row.iter().zip(v.iter()).map(|(&m, &v)| m * v).sum();
+    }
+    result
+}
+
+/// Population after `days`, computed in `O(log days)` via matrix
+/// exponentiation instead of `progress_fishes`'s day-by-day simulation, so
+/// a day count like `10^9` is still answerable in reasonable time. Models
+/// the system as the 9x9 transition over the timer-bucket state vector
+/// (`v[i]` = count of fish with timer `i`) described by
+/// `transition_matrix`, raises it to `days` via repeated squaring, and
+/// sums the resulting bucket counts. `progress_fishes` remains the more
+/// direct choice for small `days`.
+fn population_after(fishes: &HashMap<u8, usize>, days: u64) -> u128 {
+    let v = bucket_vector(fishes);
+    let m = matrix_pow(transition_matrix(), days);
+    apply(&m, &v).into_iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_fishes() -> HashMap<u8, usize> {
+        let mut fishes = HashMap::new();
+        for &timer in &[3u8, 4, 3, 1, 2] {
+            *fishes.entry(timer).or_insert(0) += 1;
+        }
+        fishes
+    }
+
+    #[test]
+    fn test_population_after_matches_known_totals() {
+        assert_eq!(population_after(&example_fishes(), 18), 26);
+        assert_eq!(population_after(&example_fishes(), 80), 5934);
+    }
+
+    #[test]
+    fn test_population_after_matches_iterative_progress() {
+        for days in [0, 1, 18, 80, 256] {
+            let mut iterative = example_fishes();
+            progress_fishes(&mut iterative, days as u32);
+            let expected: u128 = iterative.values().sum::<usize>() as u128;
+            assert_eq!(population_after(&example_fishes(), days), expected);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! A reusable 2-D grid: a flat, row-major `Vec<T>` plus `width`/`height`,
+//! so puzzles that parse input into a rectangular field of cells share one
+//! tested implementation of bounds-checked and wraparound neighbor lookup
+//! instead of each hand-rolling its own.
+
+use std::collections::HashMap;
+
+/// A row-major grid of `width * height` cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, cells: Vec<T>) -> Self {
+        assert_eq!(cells.len(), width * height, "cells must fill width * height");
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The flat index of `(row, col)`. Does not bounds-check.
+    pub fn index(&self, row: usize, col: usize) -> usize {
+        row * self.width + col
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        if row < self.height && col < self.width {
+            Some(&self.cells[self.index(row, col)])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if row < self.height && col < self.width {
+            let i = self.index(row, col);
+            Some(&mut self.cells[i])
+        } else {
+            None
+        }
+    }
+
+    /// The up to 4 orthogonal neighbors of `(row, col)` that lie within the
+    /// grid.
+    pub fn neighbors4(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        const DELTAS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        in_bounds_neighbors(row, col, self.width, self.height, &DELTAS)
+    }
+
+    /// The up to 8 orthogonal/diagonal neighbors of `(row, col)` that lie
+    /// within the grid.
+    pub fn neighbors8(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        in_bounds_neighbors(row, col, self.width, self.height, &EIGHT_DELTAS)
+    }
+
+    /// The 8 orthogonal/diagonal neighbors of `(row, col)`, wrapping around
+    /// the edges of the grid as if it tiled a torus.
+    pub fn neighbors8_wrapping(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width, self.height);
+        EIGHT_DELTAS.iter().map(move |(dr, dc)| {
+            let r = (row as isize + dr).rem_euclid(height as isize) as usize;
+            let c = (col as isize + dc).rem_euclid(width as isize) as usize;
+            (r, c)
+        })
+    }
+}
+
+/// A sparse point-coverage counter: every point added is tallied, so
+/// puzzles that draw many (possibly overlapping, possibly unbounded)
+/// lines or dots onto an integer plane can count overlaps and render the
+/// result without each hand-rolling its own `HashMap`/`HashSet` logic.
+/// Unlike `Grid<T>`, which needs its fixed `width`/`height` up front,
+/// `CoverageGrid` only knows its extent once points have been added.
+#[derive(Debug, Clone, Default)]
+pub struct CoverageGrid {
+    counts: HashMap<(i32, i32), usize>,
+}
+
+impl CoverageGrid {
+    pub fn new() -> Self {
+        CoverageGrid::default()
+    }
+
+    /// Add one unit of coverage at `point`.
+    pub fn add_point(&mut self, point: (i32, i32)) {
+        *self.counts.entry(point).or_insert(0) += 1;
+    }
+
+    /// Add one unit of coverage at every point in `points`.
+    pub fn add_points(&mut self, points: impl IntoIterator<Item = (i32, i32)>) {
+        for point in points {
+            self.add_point(point);
+        }
+    }
+
+    /// The number of points whose coverage count is at least `threshold`.
+    pub fn count_overlaps(&self, threshold: usize) -> usize {
+        self.counts.values().filter(|&&count| count >= threshold).count()
+    }
+
+    /// Render the grid's bounding box as a multi-line string, marking
+    /// covered points with `#` and everything else with `.`. Empty grids
+    /// render as an empty string.
+    pub fn render(&self) -> String {
+        if self.counts.is_empty() {
+            return String::new();
+        }
+
+        let min_x = self.counts.keys().map(|(x, _)| *x).min().unwrap();
+        let max_x = self.counts.keys().map(|(x, _)| *x).max().unwrap();
+        let min_y = self.counts.keys().map(|(_, y)| *y).min().unwrap();
+        let max_y = self.counts.keys().map(|(_, y)| *y).max().unwrap();
+
+        (min_y..=max_y)
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| if self.counts.contains_key(&(x, y)) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+const EIGHT_DELTAS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+fn in_bounds_neighbors<'a>(
+    row: usize,
+    col: usize,
+    width: usize,
+    height: usize,
+    deltas: &'a [(isize, isize)],
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    deltas.iter().filter_map(move |(dr, dc)| {
+        let r = row as isize + dr;
+        let c = col as isize + dc;
+        if r >= 0 && r < height as isize && c >= 0 && c < width as isize {
+            Some((r as usize, c as usize))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Grid<u32> {
+        Grid::new(3, 2, vec![0, 1, 2, 3, 4, 5])
+    }
+
+    #[test]
+    fn test_get_and_index() {
+        let grid = sample();
+        assert_eq!(grid.get(1, 2), Some(&5));
+        assert_eq!(grid.get(2, 0), None);
+        assert_eq!(grid.index(1, 2), 5);
+    }
+
+    #[test]
+    fn test_neighbors4_corner() {
+        let grid = sample();
+        let mut neighbors: Vec<_> = grid.neighbors4(0, 0).collect();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_neighbors8_corner() {
+        let grid = sample();
+        let mut neighbors: Vec<_> = grid.neighbors8(0, 0).collect();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![(0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_neighbors8_wrapping_from_corner() {
+        let grid = sample();
+        let mut neighbors: Vec<_> = grid.neighbors8_wrapping(0, 0).collect();
+        neighbors.sort();
+        // a 3-wide, 2-tall grid wraps (0,0)'s 8 neighbors back onto every
+        // other cell: the row-0 cells once each, and the row-1 cells twice
+        // each (reached both from above and from below).
+        assert_eq!(
+            neighbors,
+            vec![(0, 1), (0, 2), (1, 0), (1, 0), (1, 1), (1, 1), (1, 2), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn test_coverage_grid_count_overlaps() {
+        let mut grid = CoverageGrid::new();
+        grid.add_points([(0, 0), (1, 0), (1, 0), (2, 0)]);
+
+        // (1, 0) was added twice, so it's the only point covered at least twice.
+        assert_eq!(grid.count_overlaps(2), 1);
+        // all three distinct points are covered at least once.
+        assert_eq!(grid.count_overlaps(1), 3);
+    }
+
+    #[test]
+    fn test_coverage_grid_render() {
+        let mut grid = CoverageGrid::new();
+        grid.add_points([(0, 0), (2, 0), (1, 1)]);
+
+        assert_eq!(grid.render(), "#.#\n.#.");
+    }
+
+    #[test]
+    fn test_coverage_grid_render_empty() {
+        let grid = CoverageGrid::new();
+        assert_eq!(grid.render(), "");
+    }
+}
@@ -1,5 +1,10 @@
 #![allow(dead_code)]
 
+pub mod automaton;
+pub mod grid;
+pub mod mincostflow;
+pub mod solution;
+
 use std::fmt::Debug;
 use std::io::BufRead;
 use std::str::FromStr;
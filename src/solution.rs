@@ -0,0 +1,21 @@
+//! A common shape for a day's two parts, so a registry of
+//! `Box<dyn Solution>` can drive any day from its raw input text instead of
+//! each day hand-rolling its own `println!`s and hardcoded input path.
+
+use std::error::Error;
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+pub trait Solution {
+    fn part1(&self, input: &str) -> Result<String>;
+    fn part2(&self, input: &str) -> Result<String>;
+
+    /// Run both parts and format them the way the CLI runner prints them.
+    fn run(&self, input: &str) -> Result<String> {
+        Ok(format!(
+            "part 1: {}\npart 2: {}",
+            self.part1(input)?,
+            self.part2(input)?
+        ))
+    }
+}